@@ -1,8 +1,13 @@
 use sha2::{Digest, Sha256};
 
 pub fn compute_hash(input: &str) -> String {
+    compute_bytes_hash(input.as_bytes())
+}
+
+/// 文件内容的 SHA256，用于按内容区分缓存
+pub fn compute_bytes_hash(bytes: &[u8]) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(input.as_bytes());
+    hasher.update(bytes);
     let digest = hasher.finalize();
     hex::encode(digest)
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::types::{DescriptionExtras, ImageProfile, Loras};
+
 #[derive(Serialize, Deserialize)]
 pub struct ImageCacheMetadata {
     pub original_url: String,
@@ -16,6 +18,10 @@ pub struct ImageCacheMetadata {
     pub size: Option<usize>,
     #[serde(default)]
     pub aspect_ratio: Option<f64>,
+    #[serde(default, skip_serializing_if = "DescriptionExtras::is_empty")]
+    pub extras: DescriptionExtras,
+    #[serde(default)]
+    pub profile: Option<ImageProfile>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -40,6 +46,7 @@ pub struct GeneratedImageCacheMetadata {
     pub cache_key_input: String,
     pub cached_image_key: String,
     pub cached_image_url: String,
+    pub upstream_image_url: String,
     pub mime_type: String,
     pub created_at: String,
 }
@@ -49,6 +56,7 @@ pub struct EditedImageCacheMetadata {
     pub cache_key_input: String,
     pub cached_image_key: String,
     pub cached_image_url: String,
+    pub upstream_image_url: String,
     pub mime_type: String,
     pub created_at: String,
 }
@@ -62,6 +70,12 @@ pub struct AiImageRecord {
     pub aspect_ratio: Option<String>,
     pub resolution: Option<String>,
     pub steps: Option<u32>,
+    #[serde(default)]
+    pub seed: Option<u32>,
+    #[serde(default)]
+    pub loras: Option<Loras>,
     pub source_image_url: Option<String>,
+    #[serde(default)]
+    pub source_image_urls: Vec<String>,
     pub created_at: String,
 }
@@ -5,7 +5,7 @@ pub mod ai_image_storage;
 
 pub use storage::LocalFileStorage;
 pub use metadata::*;
-pub use hash::compute_hash;
+pub use hash::{compute_bytes_hash, compute_hash};
 pub use ai_image_storage::{save_ai_image_record, list_ai_image_records};
 
 pub fn get_extension_from_mime_type(mime_type: &str) -> &str {
@@ -43,6 +43,23 @@ impl LocalFileStorage {
     }
 
     pub fn get_public_url(&self, key: &str) -> String {
+        let trimmed = self.normalized_base_url();
+        let key = key.trim_start_matches('/');
+        format!("{trimmed}/{key}")
+    }
+
+    /// 将本服务的 /cache 公开地址还原为缓存 key，非本服务地址返回 None
+    pub fn key_from_public_url(&self, url: &str) -> Option<String> {
+        let base = format!("{}/", self.normalized_base_url());
+        let key = url.strip_prefix(&base)?;
+        let key = key.split(['?', '#']).next().unwrap_or(key);
+        if key.is_empty() || key.split('/').any(|part| part == "..") {
+            return None;
+        }
+        Some(key.to_string())
+    }
+
+    fn normalized_base_url(&self) -> String {
         let mut trimmed = self.base_url.trim_end_matches('/').to_string();
         while trimmed.starts_with("http://http://") {
             trimmed = trimmed.replacen("http://http://", "http://", 1);
@@ -56,8 +73,7 @@ impl LocalFileStorage {
         while trimmed.starts_with("https://http://") {
             trimmed = trimmed.replacen("https://http://", "http://", 1);
         }
-        let key = key.trim_start_matches('/');
-        format!("{trimmed}/{key}")
+        trimmed
     }
 
     pub fn get_image_prefix(hash: &str) -> String {
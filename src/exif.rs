@@ -0,0 +1,442 @@
+use crate::types::GpsPosition;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF: u16 = 0x0005;
+const TAG_GPS_ALTITUDE: u16 = 0x0006;
+
+/// 单个 IFD 最多读取的条目数，防止损坏的数据导致长时间循环
+const MAX_IFD_ENTRIES: usize = 512;
+
+/// 从 EXIF 中读取的拍摄信息，缺失的字段为 None
+#[derive(Debug, Clone, Default)]
+pub struct ExifData {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    pub software: Option<String>,
+    /// 拍摄时间，优先取 DateTimeOriginal，格式为 `YYYY-MM-DDTHH:MM:SS`（无时区）
+    pub date_time: Option<String>,
+    pub gps: Option<GpsPosition>,
+}
+
+/// 解析以 TIFF 头（`II*\0` 或 `MM\0*`）开头的 EXIF 数据块，即 `ImageDecoder::exif_metadata` 的返回值
+pub fn parse_exif(chunk: &[u8]) -> ExifData {
+    let Some(reader) = TiffReader::new(chunk) else {
+        return ExifData::default();
+    };
+    let Some(ifd0) = reader.u32(4).map(|offset| reader.entries(offset as usize)) else {
+        return ExifData::default();
+    };
+    let find = |entries: &[IfdEntry], tag: u16| entries.iter().find(|entry| entry.tag == tag).copied();
+    let sub_ifd = |tag: u16| {
+        find(&ifd0, tag)
+            .and_then(|entry| reader.unsigned(entry))
+            .map(|offset| reader.entries(offset as usize))
+            .unwrap_or_default()
+    };
+    let exif_ifd = sub_ifd(TAG_EXIF_IFD);
+    let gps_ifd = sub_ifd(TAG_GPS_IFD);
+    let ascii = |entries: &[IfdEntry], tag: u16| find(entries, tag).and_then(|entry| reader.ascii(entry));
+
+    let date_time = ascii(&exif_ifd, TAG_DATE_TIME_ORIGINAL)
+        .or_else(|| ascii(&ifd0, TAG_DATE_TIME))
+        .map(|value| normalize_date_time(&value));
+    let coordinate = |value_tag: u16, ref_tag: u16, negative_ref: &str| {
+        let parts = find(&gps_ifd, value_tag).and_then(|entry| reader.rationals(entry))?;
+        let degrees = parts.first()? + parts.get(1).unwrap_or(&0.0) / 60.0
+            + parts.get(2).unwrap_or(&0.0) / 3600.0;
+        if !degrees.is_finite() {
+            return None;
+        }
+        let negative = ascii(&gps_ifd, ref_tag)
+            .is_some_and(|reference| reference.eq_ignore_ascii_case(negative_ref));
+        Some(if negative { -degrees } else { degrees })
+    };
+    let gps = match (
+        coordinate(TAG_GPS_LATITUDE, TAG_GPS_LATITUDE_REF, "S"),
+        coordinate(TAG_GPS_LONGITUDE, TAG_GPS_LONGITUDE_REF, "W"),
+    ) {
+        (Some(latitude), Some(longitude)) => {
+            let below_sea_level = find(&gps_ifd, TAG_GPS_ALTITUDE_REF)
+                .and_then(|entry| reader.unsigned(entry))
+                == Some(1);
+            let altitude = find(&gps_ifd, TAG_GPS_ALTITUDE)
+                .and_then(|entry| reader.rationals(entry))
+                .and_then(|values| values.first().copied())
+                .filter(|altitude| altitude.is_finite())
+                .map(|altitude| if below_sea_level { -altitude } else { altitude });
+            Some(GpsPosition {
+                latitude,
+                longitude,
+                altitude,
+            })
+        }
+        _ => None,
+    };
+
+    ExifData {
+        camera_make: ascii(&ifd0, TAG_MAKE),
+        camera_model: ascii(&ifd0, TAG_MODEL),
+        lens_model: ascii(&exif_ifd, TAG_LENS_MODEL),
+        software: ascii(&ifd0, TAG_SOFTWARE),
+        date_time,
+        gps,
+    }
+}
+
+/// EXIF 时间格式为 `YYYY:MM:DD HH:MM:SS`，转为 `YYYY-MM-DDTHH:MM:SS`；格式不符时原样返回
+fn normalize_date_time(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let well_formed = bytes.len() == 19
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 | 13 | 16 => *byte == b':',
+            10 => *byte == b' ',
+            _ => byte.is_ascii_digit(),
+        });
+    if !well_formed {
+        return value.to_string();
+    }
+    format!(
+        "{}-{}-{}T{}",
+        &value[0..4],
+        &value[5..7],
+        &value[8..10],
+        &value[11..19]
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// 条目中 4 字节值/偏移字段的位置
+    value_position: usize,
+}
+
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(0..4)? {
+            [0x49, 0x49, 42, 0] => true,
+            [0x4D, 0x4D, 0, 42] => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn entries(&self, offset: usize) -> Vec<IfdEntry> {
+        let Some(count) = self.u16(offset) else {
+            return Vec::new();
+        };
+        (0..(count as usize).min(MAX_IFD_ENTRIES))
+            .map_while(|index| {
+                let position = offset + 2 + index * 12;
+                Some(IfdEntry {
+                    tag: self.u16(position)?,
+                    kind: self.u16(position + 2)?,
+                    count: self.u32(position + 4)?,
+                    value_position: position + 8,
+                })
+            })
+            .collect()
+    }
+
+    /// 值的起始位置与字节数：不超过 4 字节时直接存放在条目中，否则条目中存放偏移
+    fn value_range(&self, entry: IfdEntry) -> Option<(usize, usize)> {
+        let unit: usize = match entry.kind {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => return None,
+        };
+        let size = unit.checked_mul(entry.count as usize)?;
+        let start = if size <= 4 {
+            entry.value_position
+        } else {
+            self.u32(entry.value_position)? as usize
+        };
+        Some((start, size))
+    }
+
+    fn value_bytes(&self, entry: IfdEntry) -> Option<&'a [u8]> {
+        let (start, size) = self.value_range(entry)?;
+        self.data.get(start..start.checked_add(size)?)
+    }
+
+    fn ascii(&self, entry: IfdEntry) -> Option<String> {
+        if entry.kind != 2 {
+            return None;
+        }
+        let bytes = self.value_bytes(entry)?;
+        let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+        let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn unsigned(&self, entry: IfdEntry) -> Option<u32> {
+        match entry.kind {
+            1 | 7 => self.value_bytes(entry)?.first().map(|byte| *byte as u32),
+            3 => self.u16(entry.value_position).map(u32::from),
+            4 => self.u32(entry.value_position),
+            _ => None,
+        }
+    }
+
+    /// 任一分量越界或分母为 0 时整个值无效，避免度分秒错位
+    fn rationals(&self, entry: IfdEntry) -> Option<Vec<f64>> {
+        if entry.kind != 5 && entry.kind != 10 {
+            return None;
+        }
+        let (start, _) = self.value_range(entry)?;
+        (0..(entry.count as usize).min(self.data.len() / 8))
+            .map(|index| {
+                let numerator = self.u32(start + index * 8)?;
+                let denominator = self.u32(start + index * 8 + 4)?;
+                let (numerator, denominator) = if entry.kind == 10 {
+                    (numerator as i32 as f64, denominator as i32 as f64)
+                } else {
+                    (numerator as f64, denominator as f64)
+                };
+                (denominator != 0.0).then(|| numerator / denominator)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Value {
+        Bytes(Vec<u8>),
+        /// 指向第几个 IFD 的偏移
+        Ifd(usize),
+    }
+
+    struct Entry {
+        tag: u16,
+        kind: u16,
+        count: u32,
+        value: Value,
+    }
+
+    struct Builder {
+        little_endian: bool,
+    }
+
+    impl Builder {
+        fn u16(&self, value: u16) -> [u8; 2] {
+            if self.little_endian {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            }
+        }
+
+        fn u32(&self, value: u32) -> [u8; 4] {
+            if self.little_endian {
+                value.to_le_bytes()
+            } else {
+                value.to_be_bytes()
+            }
+        }
+
+        fn ascii(&self, tag: u16, text: &str) -> Entry {
+            let mut bytes = text.as_bytes().to_vec();
+            bytes.push(0);
+            Entry {
+                tag,
+                kind: 2,
+                count: bytes.len() as u32,
+                value: Value::Bytes(bytes),
+            }
+        }
+
+        fn byte(&self, tag: u16, value: u8) -> Entry {
+            Entry {
+                tag,
+                kind: 1,
+                count: 1,
+                value: Value::Bytes(vec![value]),
+            }
+        }
+
+        fn rationals(&self, tag: u16, values: &[(u32, u32)]) -> Entry {
+            let bytes = values
+                .iter()
+                .flat_map(|(numerator, denominator)| {
+                    [self.u32(*numerator), self.u32(*denominator)].concat()
+                })
+                .collect();
+            Entry {
+                tag,
+                kind: 5,
+                count: values.len() as u32,
+                value: Value::Bytes(bytes),
+            }
+        }
+
+        fn pointer(&self, tag: u16, ifd: usize) -> Entry {
+            Entry {
+                tag,
+                kind: 4,
+                count: 1,
+                value: Value::Ifd(ifd),
+            }
+        }
+
+        /// 按顺序排布各个 IFD，超过 4 字节的值紧跟在所属 IFD 之后
+        fn build(&self, ifds: &[Vec<Entry>]) -> Vec<u8> {
+            let out_of_line = |entry: &Entry| match &entry.value {
+                Value::Bytes(bytes) if bytes.len() > 4 => bytes.len() + bytes.len() % 2,
+                _ => 0,
+            };
+            let mut offsets = Vec::new();
+            let mut position = 8;
+            for ifd in ifds {
+                offsets.push(position);
+                position += 2 + 12 * ifd.len() + 4 + ifd.iter().map(out_of_line).sum::<usize>();
+            }
+
+            let mut data = if self.little_endian { b"II".to_vec() } else { b"MM".to_vec() };
+            data.extend(self.u16(42));
+            data.extend(self.u32(8));
+            for (ifd, offset) in ifds.iter().zip(&offsets) {
+                let mut extra_position = offset + 2 + 12 * ifd.len() + 4;
+                let mut extra = Vec::new();
+                data.extend(self.u16(ifd.len() as u16));
+                for entry in ifd {
+                    data.extend(self.u16(entry.tag));
+                    data.extend(self.u16(entry.kind));
+                    data.extend(self.u32(entry.count));
+                    match &entry.value {
+                        Value::Ifd(index) => data.extend(self.u32(offsets[*index] as u32)),
+                        Value::Bytes(bytes) if bytes.len() <= 4 => {
+                            let mut inline = bytes.clone();
+                            inline.resize(4, 0);
+                            data.extend(inline);
+                        }
+                        Value::Bytes(bytes) => {
+                            data.extend(self.u32(extra_position as u32));
+                            extra.extend(bytes);
+                            if bytes.len() % 2 == 1 {
+                                extra.push(0);
+                            }
+                            extra_position += out_of_line(entry);
+                        }
+                    }
+                }
+                data.extend(self.u32(0));
+                data.extend(extra);
+            }
+            data
+        }
+    }
+
+    fn sample(builder: &Builder, latitude_seconds: (u32, u32)) -> Vec<u8> {
+        builder.build(&[
+            vec![
+                // 超过 4 字节，存放在偏移处
+                builder.ascii(TAG_MAKE, "Canon"),
+                // 不超过 4 字节，直接存放在条目中
+                builder.ascii(TAG_MODEL, "X1"),
+                builder.pointer(TAG_EXIF_IFD, 1),
+                builder.pointer(TAG_GPS_IFD, 2),
+            ],
+            vec![
+                builder.ascii(TAG_DATE_TIME_ORIGINAL, "2024:05:06 07:08:09"),
+                builder.ascii(TAG_LENS_MODEL, "RF 50mm"),
+            ],
+            vec![
+                builder.ascii(TAG_GPS_LATITUDE_REF, "S"),
+                builder.rationals(TAG_GPS_LATITUDE, &[(30, 1), (15, 1), latitude_seconds]),
+                builder.ascii(TAG_GPS_LONGITUDE_REF, "W"),
+                builder.rationals(TAG_GPS_LONGITUDE, &[(120, 1), (30, 1), (0, 1)]),
+                builder.byte(TAG_GPS_ALTITUDE_REF, 1),
+                builder.rationals(TAG_GPS_ALTITUDE, &[(25, 2)]),
+            ],
+        ])
+    }
+
+    #[test]
+    fn parse_exif_reads_both_byte_orders() {
+        for little_endian in [true, false] {
+            let exif = parse_exif(&sample(&Builder { little_endian }, (36, 1)));
+            assert_eq!(exif.camera_make.as_deref(), Some("Canon"));
+            assert_eq!(exif.camera_model.as_deref(), Some("X1"));
+            assert_eq!(exif.lens_model.as_deref(), Some("RF 50mm"));
+            assert_eq!(exif.date_time.as_deref(), Some("2024-05-06T07:08:09"));
+            let gps = exif.gps.expect("gps");
+            assert!((gps.latitude + (30.0 + 15.0 / 60.0 + 36.0 / 3600.0)).abs() < 1e-9);
+            assert!((gps.longitude + 120.5).abs() < 1e-9);
+            assert_eq!(gps.altitude, Some(-12.5));
+        }
+    }
+
+    #[test]
+    fn parse_exif_rejects_zero_denominator_coordinates() {
+        let exif = parse_exif(&sample(&Builder { little_endian: true }, (36, 0)));
+        assert_eq!(exif.camera_make.as_deref(), Some("Canon"));
+        assert!(exif.gps.is_none());
+    }
+
+    #[test]
+    fn parse_exif_handles_truncated_and_cyclic_ifds() {
+        let data = sample(&Builder { little_endian: false }, (36, 1));
+        for len in 0..data.len() {
+            parse_exif(&data[..len]);
+        }
+        assert!(parse_exif(&data[..20]).camera_make.is_none());
+        assert!(parse_exif(b"not exif").camera_make.is_none());
+
+        // 子 IFD 指回 IFD0 时只读取一次，不会循环
+        let builder = Builder { little_endian: true };
+        let cyclic = builder.build(&[vec![
+            builder.ascii(TAG_MAKE, "Canon"),
+            builder.pointer(TAG_EXIF_IFD, 0),
+            builder.pointer(TAG_GPS_IFD, 0),
+        ]]);
+        let exif = parse_exif(&cyclic);
+        assert_eq!(exif.camera_make.as_deref(), Some("Canon"));
+        assert!(exif.lens_model.is_none());
+        assert!(exif.gps.is_none());
+    }
+}
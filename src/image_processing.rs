@@ -1,5 +1,13 @@
+use std::io::Cursor;
+
 use anyhow::{anyhow, Result};
-use image::{DynamicImage, ImageFormat, RgbaImage, GenericImageView};
+use image::{
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, RgbaImage, imageops::FilterType,
+};
+use image::metadata::Orientation;
+
+use crate::exif;
+pub use crate::types::{ExifSummary, ImageProfile};
 
 const BYTES_PER_PIXEL: usize = 4;
 
@@ -110,6 +118,42 @@ pub fn crop_pixels(
     output
 }
 
+/// 按像素矩形 (x, y, width, height) 裁剪 RGBA 像素
+pub fn crop_rect(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    rect: (u32, u32, u32, u32),
+) -> Result<Vec<u8>> {
+    let expected_len = width
+        .saturating_mul(height)
+        .saturating_mul(BYTES_PER_PIXEL as u32) as usize;
+    if pixels.len() != expected_len {
+        return Err(anyhow!(
+            "pixel buffer size mismatch: expected {expected_len} bytes for {width}x{height}, got {}",
+            pixels.len()
+        ));
+    }
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    if rect_width == 0
+        || rect_height == 0
+        || rect_x.saturating_add(rect_width) > width
+        || rect_y.saturating_add(rect_height) > height
+    {
+        return Err(anyhow!(
+            "crop rect ({rect_x}, {rect_y}, {rect_width}, {rect_height}) is empty or outside {width}x{height}"
+        ));
+    }
+
+    let row_len = (rect_width * BYTES_PER_PIXEL as u32) as usize;
+    let mut output = Vec::with_capacity(row_len * rect_height as usize);
+    for y in rect_y..rect_y + rect_height {
+        let start = ((y * width + rect_x) * BYTES_PER_PIXEL as u32) as usize;
+        output.extend_from_slice(&pixels[start..start + row_len]);
+    }
+    Ok(output)
+}
+
 pub fn get_cropped_dimensions(
     width: u32,
     height: u32,
@@ -134,15 +178,451 @@ pub fn get_cropped_dimensions(
     vec![new_width, new_height]
 }
 
+pub fn resize_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    new_width: u32,
+    new_height: u32,
+) -> Result<Vec<u8>> {
+    let rgba = RgbaImage::from_raw(width, height, pixels.to_vec())
+        .ok_or_else(|| anyhow!("invalid rgba buffer"))?;
+    if width == new_width && height == new_height {
+        return Ok(rgba.into_raw());
+    }
+    let resized = image::imageops::resize(&rgba, new_width, new_height, FilterType::Triangle);
+    Ok(resized.into_raw())
+}
+
+/// 长边超过 `max_edge` 时按比例缩小，返回新的像素与尺寸
+pub fn downscale_to_max_edge(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    max_edge: u32,
+) -> Result<(Vec<u8>, u32, u32)> {
+    if max_edge == 0 || width.max(height) <= max_edge {
+        return Ok((pixels.to_vec(), width, height));
+    }
+    let scale = max_edge as f64 / width.max(height) as f64;
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+    let resized = resize_pixels(pixels, width, height, new_width, new_height)?;
+    Ok((resized, new_width, new_height))
+}
+
+/// 生成单通道蒙版（255 表示需要编辑的区域），矩形为像素坐标 (x, y, width, height)
+pub fn rasterize_rect_mask(width: u32, height: u32, rect: (u32, u32, u32, u32)) -> Vec<u8> {
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    let mut mask = vec![0u8; (width * height) as usize];
+    let end_x = rect_x.saturating_add(rect_width).min(width);
+    let end_y = rect_y.saturating_add(rect_height).min(height);
+    for y in rect_y.min(height)..end_y {
+        let row = (y * width) as usize;
+        mask[row + rect_x.min(width) as usize..row + end_x as usize].fill(255);
+    }
+    mask
+}
+
+/// 将 RGBA 蒙版图转换为单通道蒙版：亮度过半且不透明的像素视为编辑区域
+pub fn mask_from_pixels(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    let expected_len = width
+        .saturating_mul(height)
+        .saturating_mul(BYTES_PER_PIXEL as u32) as usize;
+    if pixels.len() != expected_len {
+        return Err(anyhow!(
+            "mask buffer size mismatch: expected {expected_len} bytes for {width}x{height}, got {}",
+            pixels.len()
+        ));
+    }
+    Ok(pixels
+        .chunks_exact(BYTES_PER_PIXEL)
+        .map(|pixel| {
+            let luma = (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114)
+                / 1000;
+            if luma >= 128 && pixel[3] >= 128 { 255 } else { 0 }
+        })
+        .collect())
+}
+
+/// 按蒙版合成：蒙版为 0 的像素原样保留 `original`，其余取 `edited`
+pub fn composite_with_mask(original: &[u8], edited: &[u8], mask: &[u8]) -> Result<Vec<u8>> {
+    if original.len() != edited.len() {
+        return Err(anyhow!(
+            "edited buffer size mismatch: expected {} bytes, got {}",
+            original.len(),
+            edited.len()
+        ));
+    }
+    if original.len() != mask.len() * BYTES_PER_PIXEL {
+        return Err(anyhow!(
+            "mask size mismatch: expected {} pixels, got {}",
+            original.len() / BYTES_PER_PIXEL,
+            mask.len()
+        ));
+    }
+    let mut output = original.to_vec();
+    for (index, value) in mask.iter().enumerate() {
+        if *value != 0 {
+            let start = index * BYTES_PER_PIXEL;
+            output[start..start + BYTES_PER_PIXEL]
+                .copy_from_slice(&edited[start..start + BYTES_PER_PIXEL]);
+        }
+    }
+    Ok(output)
+}
+
+/// 3x5 点阵数字，每行低 3 位依次表示左、中、右像素
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// 填充像素矩形 (x, y, width, height)，超出画布的部分会被裁掉
+pub fn fill_rect(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    rect: (u32, u32, u32, u32),
+    color: [u8; 4],
+) {
+    if pixels.len() != (width * height) as usize * BYTES_PER_PIXEL {
+        return;
+    }
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    let end_x = rect_x.saturating_add(rect_width).min(width);
+    let end_y = rect_y.saturating_add(rect_height).min(height);
+    for y in rect_y.min(height)..end_y {
+        for x in rect_x.min(width)..end_x {
+            let index = ((y * width + x) as usize) * BYTES_PER_PIXEL;
+            pixels[index..index + BYTES_PER_PIXEL].copy_from_slice(&color);
+        }
+    }
+}
+
+/// 绘制矩形边框，线宽向框内延伸
+pub fn draw_rect_outline(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    rect: (u32, u32, u32, u32),
+    thickness: u32,
+    color: [u8; 4],
+) {
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    let thickness = thickness.max(1).min(rect_width).min(rect_height);
+    fill_rect(pixels, width, height, (rect_x, rect_y, rect_width, thickness), color);
+    fill_rect(
+        pixels,
+        width,
+        height,
+        (rect_x, rect_y + rect_height - thickness, rect_width, thickness),
+        color,
+    );
+    fill_rect(pixels, width, height, (rect_x, rect_y, thickness, rect_height), color);
+    fill_rect(
+        pixels,
+        width,
+        height,
+        (rect_x + rect_width - thickness, rect_y, thickness, rect_height),
+        color,
+    );
+}
+
+/// 在 (x, y) 处绘制带底色的数字标签，返回标签占用的像素尺寸
+pub fn draw_number_label(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    (x, y): (u32, u32),
+    number: usize,
+    scale: u32,
+    background: [u8; 4],
+) -> (u32, u32) {
+    let scale = scale.max(1);
+    let digits: Vec<usize> = number
+        .to_string()
+        .bytes()
+        .map(|digit| (digit - b'0') as usize)
+        .collect();
+    let padding = scale;
+    let label_width = digits.len() as u32 * 4 * scale - scale + padding * 2;
+    let label_height = 5 * scale + padding * 2;
+    fill_rect(pixels, width, height, (x, y, label_width, label_height), background);
+    let foreground = if luma(&background) > 140 {
+        [0, 0, 0, 255]
+    } else {
+        [255, 255, 255, 255]
+    };
+    for (position, digit) in digits.iter().enumerate() {
+        let origin_x = x + padding + position as u32 * 4 * scale;
+        for (row, bits) in DIGIT_GLYPHS[*digit].iter().enumerate() {
+            for column in 0..3u32 {
+                if bits & (0b100 >> column) != 0 {
+                    fill_rect(
+                        pixels,
+                        width,
+                        height,
+                        (origin_x + column * scale, y + padding + row as u32 * scale, scale, scale),
+                        foreground,
+                    );
+                }
+            }
+        }
+    }
+    (label_width, label_height)
+}
+
+/// 对像素矩形区域做高斯模糊，模糊只取区域内像素，不会把区域外内容带入
+pub fn blur_rect(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    rect: (u32, u32, u32, u32),
+    sigma: f32,
+) -> Result<()> {
+    let region = crop_rect(pixels, width, height, rect)?;
+    let region = RgbaImage::from_raw(rect.2, rect.3, region)
+        .ok_or_else(|| anyhow!("invalid rgba buffer"))?;
+    let blurred = image::imageops::blur(&region, sigma.max(0.1));
+    paste_rect(pixels, width, height, rect, blurred.as_raw());
+    Ok(())
+}
+
+/// 将像素矩形区域马赛克化，每个 `block` x `block` 的格子填充其平均色
+pub fn pixelate_rect(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    rect: (u32, u32, u32, u32),
+    block: u32,
+) {
+    if pixels.len() != (width * height) as usize * BYTES_PER_PIXEL {
+        return;
+    }
+    let block = block.max(1);
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    let end_x = rect_x.saturating_add(rect_width).min(width);
+    let end_y = rect_y.saturating_add(rect_height).min(height);
+    for cell_y in (rect_y.min(end_y)..end_y).step_by(block as usize) {
+        for cell_x in (rect_x.min(end_x)..end_x).step_by(block as usize) {
+            let cell_end_x = (cell_x + block).min(end_x);
+            let cell_end_y = (cell_y + block).min(end_y);
+            let mut sum = [0u64; BYTES_PER_PIXEL];
+            for y in cell_y..cell_end_y {
+                for x in cell_x..cell_end_x {
+                    let index = ((y * width + x) as usize) * BYTES_PER_PIXEL;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += pixels[index + channel] as u64;
+                    }
+                }
+            }
+            let count = ((cell_end_x - cell_x) * (cell_end_y - cell_y)) as u64;
+            let average = sum.map(|total| (total / count) as u8);
+            fill_rect(
+                pixels,
+                width,
+                height,
+                (cell_x, cell_y, cell_end_x - cell_x, cell_end_y - cell_y),
+                average,
+            );
+        }
+    }
+}
+
+/// 将 `region`（尺寸与矩形一致的 RGBA 像素）写回原图对应位置
+fn paste_rect(pixels: &mut [u8], width: u32, height: u32, rect: (u32, u32, u32, u32), region: &[u8]) {
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    if rect_x + rect_width > width
+        || rect_y + rect_height > height
+        || region.len() != (rect_width * rect_height) as usize * BYTES_PER_PIXEL
+    {
+        return;
+    }
+    let row_len = rect_width as usize * BYTES_PER_PIXEL;
+    for row in 0..rect_height {
+        let src = row as usize * row_len;
+        let dst = (((rect_y + row) * width + rect_x) as usize) * BYTES_PER_PIXEL;
+        pixels[dst..dst + row_len].copy_from_slice(&region[src..src + row_len]);
+    }
+}
+
+/// 逐像素差异热力图：底图为 `before` 的暗化灰度，差异越大越红
+pub fn diff_heatmap(before: &[u8], after: &[u8]) -> Vec<u8> {
+    if before.len() != after.len() {
+        return Vec::new();
+    }
+    let mut output = Vec::with_capacity(before.len());
+    for (a, b) in before
+        .chunks_exact(BYTES_PER_PIXEL)
+        .zip(after.chunks_exact(BYTES_PER_PIXEL))
+    {
+        let diff = (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0);
+        let base = (luma(a) as u32 * 3 / 10) as u8;
+        let heat = diff as u32 * 4;
+        output.extend_from_slice(&[
+            base.saturating_add(heat.min(255) as u8),
+            base.saturating_add(heat.saturating_sub(255).min(255) as u8),
+            base,
+            255,
+        ]);
+    }
+    output
+}
+
+/// 任一 RGB 通道差值超过 `threshold` 的像素占比
+pub fn changed_pixel_ratio(before: &[u8], after: &[u8], threshold: u8) -> f64 {
+    if before.len() != after.len() || before.is_empty() {
+        return 0.0;
+    }
+    let changed = before
+        .chunks_exact(BYTES_PER_PIXEL)
+        .zip(after.chunks_exact(BYTES_PER_PIXEL))
+        .filter(|(a, b)| (0..3).any(|c| a[c].abs_diff(b[c]) > threshold))
+        .count();
+    changed as f64 / (before.len() / BYTES_PER_PIXEL) as f64
+}
+
+/// RGB 三通道 PSNR（dB），两图完全一致时返回 None
+pub fn psnr(before: &[u8], after: &[u8]) -> Option<f64> {
+    if before.len() != after.len() || before.is_empty() {
+        return None;
+    }
+    let mut squared_error = 0f64;
+    for (a, b) in before
+        .chunks_exact(BYTES_PER_PIXEL)
+        .zip(after.chunks_exact(BYTES_PER_PIXEL))
+    {
+        for c in 0..3 {
+            let diff = a[c] as f64 - b[c] as f64;
+            squared_error += diff * diff;
+        }
+    }
+    let mse = squared_error / (before.len() / BYTES_PER_PIXEL * 3) as f64;
+    if mse == 0.0 {
+        return None;
+    }
+    Some(10.0 * (255.0 * 255.0 / mse).log10())
+}
+
+/// 基于亮度的 SSIM，按 8x8 不重叠窗口计算后取平均
+pub fn ssim(before: &[u8], after: &[u8], width: u32, height: u32) -> f64 {
+    const WINDOW: u32 = 8;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    if before.len() != after.len() || before.len() != (width * height) as usize * BYTES_PER_PIXEL {
+        return 0.0;
+    }
+    let luma_a: Vec<f64> = before.chunks_exact(BYTES_PER_PIXEL).map(|p| luma(p) as f64).collect();
+    let luma_b: Vec<f64> = after.chunks_exact(BYTES_PER_PIXEL).map(|p| luma(p) as f64).collect();
+    let mut total = 0f64;
+    let mut windows = 0u32;
+    for window_y in (0..height).step_by(WINDOW as usize) {
+        for window_x in (0..width).step_by(WINDOW as usize) {
+            let end_x = (window_x + WINDOW).min(width);
+            let end_y = (window_y + WINDOW).min(height);
+            let count = ((end_x - window_x) * (end_y - window_y)) as f64;
+            let (mut sum_a, mut sum_b) = (0f64, 0f64);
+            for y in window_y..end_y {
+                for x in window_x..end_x {
+                    let index = (y * width + x) as usize;
+                    sum_a += luma_a[index];
+                    sum_b += luma_b[index];
+                }
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let (mut var_a, mut var_b, mut covariance) = (0f64, 0f64, 0f64);
+            for y in window_y..end_y {
+                for x in window_x..end_x {
+                    let index = (y * width + x) as usize;
+                    let da = luma_a[index] - mean_a;
+                    let db = luma_b[index] - mean_b;
+                    var_a += da * da;
+                    var_b += db * db;
+                    covariance += da * db;
+                }
+            }
+            let (var_a, var_b, covariance) = (var_a / count, var_b / count, covariance / count);
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 { 1.0 } else { total / windows as f64 }
+}
+
+fn luma(pixel: &[u8]) -> u8 {
+    ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+}
+
 pub fn decode_image(bytes: &[u8], mime_type: &str) -> Result<(Vec<u8>, u32, u32)> {
-    let format = mime_to_format(mime_type)?;
-    let image = image::load_from_memory_with_format(bytes, format)
-        .map_err(|err| anyhow!("decode image failed: {err}"))?
-        .to_rgba8();
+    let image = load_image(bytes, mime_type)?.to_rgba8();
     let (width, height) = image.dimensions();
     Ok((image.into_raw(), width, height))
 }
 
+/// 读取 `IMAGE_AUTO_ORIENT`：是否在处理前按 EXIF 方向摆正图片，默认开启，设为 false / 0 / off 关闭
+pub fn auto_orient_enabled() -> bool {
+    std::env::var("IMAGE_AUTO_ORIENT")
+        .map(|value| {
+            !matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "false" | "0" | "off" | "no"
+            )
+        })
+        .unwrap_or(true)
+}
+
+/// 需要应用的 EXIF 方向，关闭自动摆正或没有方向信息时为 `NoTransforms`
+fn pending_orientation(decoder: &mut impl ImageDecoder) -> Orientation {
+    if !auto_orient_enabled() {
+        return Orientation::NoTransforms;
+    }
+    decoder.orientation().unwrap_or(Orientation::NoTransforms)
+}
+
+fn swaps_dimensions(orientation: Orientation) -> bool {
+    matches!(
+        orientation,
+        Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH
+    )
+}
+
+/// 解码图片并按 EXIF 方向摆正，后续的裁剪、坐标换算都基于摆正后的像素
+fn load_image(bytes: &[u8], mime_type: &str) -> Result<DynamicImage> {
+    let format = mime_to_format(mime_type)?;
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(|err| anyhow!("decode image failed: {err}"))?;
+    let orientation = pending_orientation(&mut decoder);
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|err| anyhow!("decode image failed: {err}"))?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// 原始字节是否需要摆正后才能直接交给下游（如视觉模型）使用
+pub fn needs_reorientation(bytes: &[u8], mime_type: &str) -> bool {
+    let Ok(format) = mime_to_format(mime_type) else {
+        return false;
+    };
+    ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map(|mut decoder| pending_orientation(&mut decoder) != Orientation::NoTransforms)
+        .unwrap_or(false)
+}
+
 pub fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     let rgba = RgbaImage::from_raw(width, height, pixels.to_vec())
         .ok_or_else(|| anyhow!("invalid rgba buffer"))?;
@@ -153,11 +633,178 @@ pub fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     Ok(output)
 }
 
+/// 摆正后的宽高，只读取文件头与 EXIF，不解码像素
 pub fn get_dimensions(bytes: &[u8], mime_type: &str) -> Result<(u32, u32)> {
     let format = mime_to_format(mime_type)?;
-    let image = image::load_from_memory_with_format(bytes, format)
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(|err| anyhow!("decode image failed: {err}"))?;
+    let (width, height) = decoder.dimensions();
+    if swaps_dimensions(pending_orientation(&mut decoder)) {
+        Ok((height, width))
+    } else {
+        Ok((width, height))
+    }
+}
+
+pub fn inspect_image(bytes: &[u8], mime_type: &str) -> Result<ImageProfile> {
+    let format = mime_to_format(mime_type)?;
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
         .map_err(|err| anyhow!("decode image failed: {err}"))?;
-    Ok(image.dimensions())
+    let orientation = pending_orientation(&mut decoder);
+    let (width, height) = match decoder.dimensions() {
+        (width, height) if swaps_dimensions(orientation) => (height, width),
+        dimensions => dimensions,
+    };
+    let original_color_type = decoder.original_color_type();
+    let channels = original_color_type.channel_count();
+    let has_icc_profile = decoder.icc_profile().ok().flatten().is_some();
+    let exif = decoder.exif_metadata().ok().flatten().map(|chunk| {
+        let details = exif::parse_exif(&chunk);
+        ExifSummary {
+            size: chunk.len(),
+            orientation: Orientation::from_exif_chunk(&chunk)
+                .map(|orientation| orientation.to_exif())
+                .unwrap_or(1),
+            orientation_applied: orientation != Orientation::NoTransforms,
+            camera_make: details.camera_make,
+            camera_model: details.camera_model,
+            lens_model: details.lens_model,
+            software: details.software,
+            date_time: details.date_time,
+            gps: details.gps,
+        }
+    });
+    let has_alpha = decoder.color_type().has_alpha();
+    let frame_count = count_frames(bytes, format);
+
+    Ok(ImageProfile {
+        format: format!("{format:?}").to_lowercase(),
+        mime_type: mime_type.to_string(),
+        width,
+        height,
+        total_pixels: (width as u64).saturating_mul(height as u64),
+        aspect_ratio: if height == 0 {
+            None
+        } else {
+            Some(width as f64 / height as f64)
+        },
+        size: bytes.len(),
+        color_type: format!("{original_color_type:?}"),
+        bit_depth: if channels == 0 {
+            0
+        } else {
+            original_color_type.bits_per_pixel() / channels as u16
+        },
+        channels,
+        has_alpha,
+        frame_count,
+        animated: frame_count > 1,
+        has_icc_profile,
+        exif,
+    })
+}
+
+/// 统计 GIF、APNG 与动态 WebP 的帧数，其他格式按单帧处理。
+/// 只遍历容器结构，不解码像素；结构截断时按已读到的帧数计算
+fn count_frames(bytes: &[u8], format: ImageFormat) -> u32 {
+    let frames = match format {
+        ImageFormat::Gif => count_gif_frames(bytes),
+        ImageFormat::Png => count_apng_frames(bytes),
+        ImageFormat::WebP => count_webp_frames(bytes),
+        _ => 1,
+    };
+    frames.max(1)
+}
+
+/// GIF 中每个图像描述符（0x2C）对应一帧
+fn count_gif_frames(bytes: &[u8]) -> u32 {
+    // 文件头 6 字节 + 逻辑屏幕描述符 7 字节
+    let Some(&flags) = bytes.get(10) else {
+        return 0;
+    };
+    let mut pos = 13 + color_table_len(flags);
+    let mut frames = 0u32;
+    while let Some(&introducer) = bytes.get(pos) {
+        match introducer {
+            // 扩展块：标签后接若干子块
+            0x21 => match skip_gif_sub_blocks(bytes, pos + 2) {
+                Some(next) => pos = next,
+                None => break,
+            },
+            0x2C => {
+                frames += 1;
+                let Some(&flags) = bytes.get(pos + 9) else {
+                    break;
+                };
+                // 描述符 10 字节 + 局部颜色表 + LZW 最小码长 1 字节
+                match skip_gif_sub_blocks(bytes, pos + 10 + color_table_len(flags) + 1) {
+                    Some(next) => pos = next,
+                    None => break,
+                }
+            }
+            _ => break,
+        }
+    }
+    frames
+}
+
+/// GIF 颜色表字节数，由逻辑屏幕描述符或图像描述符的标志位决定
+pub fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 << ((flags & 0x07) + 1)
+    }
+}
+
+/// 跳过以长度 0 结尾的子块序列，返回其后的位置
+pub fn skip_gif_sub_blocks(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *bytes.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            return Some(pos);
+        }
+        pos += len;
+    }
+}
+
+/// APNG 的帧数记录在 IDAT 之前的 acTL 块中，没有 acTL 时为普通 PNG
+fn count_apng_frames(bytes: &[u8]) -> u32 {
+    let mut pos = 8;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..8] {
+            b"acTL" => {
+                return bytes
+                    .get(pos + 8..pos + 12)
+                    .map(|num| u32::from_be_bytes([num[0], num[1], num[2], num[3]]))
+                    .unwrap_or(1);
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+        // 长度 + 类型 + 数据 + CRC
+        pos = pos.saturating_add(12).saturating_add(len);
+    }
+    1
+}
+
+/// 动态 WebP 中每个 ANMF 块对应一帧
+fn count_webp_frames(bytes: &[u8]) -> u32 {
+    let mut pos = 12;
+    let mut frames = 0u32;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if &header[0..4] == b"ANMF" {
+            frames += 1;
+        }
+        // 块数据按偶数字节对齐
+        pos = pos.saturating_add(8).saturating_add(len + (len & 1));
+    }
+    frames
 }
 
 pub fn detect_mime_type(bytes: &[u8]) -> Option<&'static str> {
@@ -189,3 +836,164 @@ pub fn mime_to_format(mime_type: &str) -> Result<ImageFormat> {
         _ => Err(anyhow!("unsupported mime type: {mime_type}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_rect_copies_the_requested_rows() {
+        // 3x2 图片，每个像素的 4 个通道都等于其序号
+        let pixels: Vec<u8> = (0..6u8).flat_map(|index| [index; 4]).collect();
+        let cropped = crop_rect(&pixels, 3, 2, (1, 0, 2, 2)).unwrap();
+        assert_eq!(cropped, [[1; 4], [2; 4], [4; 4], [5; 4]].concat());
+    }
+
+    #[test]
+    fn crop_rect_rejects_invalid_input() {
+        let pixels = vec![0u8; 3 * 2 * 4];
+        assert!(crop_rect(&pixels, 3, 2, (2, 0, 2, 1)).is_err());
+        assert!(crop_rect(&pixels, 3, 2, (0, 0, 0, 1)).is_err());
+        assert!(crop_rect(&pixels[..20], 3, 2, (0, 0, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn mask_from_pixels_keeps_bright_opaque_pixels() {
+        let pixels = [
+            255, 255, 255, 255, // 白色不透明
+            0, 0, 0, 255, // 黑色
+            255, 255, 255, 0, // 白色但透明
+            200, 200, 200, 200,
+        ];
+        assert_eq!(mask_from_pixels(&pixels, 2, 2).unwrap(), vec![255, 0, 0, 255]);
+        assert!(mask_from_pixels(&pixels, 3, 2).is_err());
+    }
+
+    #[test]
+    fn composite_with_mask_only_replaces_masked_pixels() {
+        let original = [1u8; 16];
+        let edited = [9u8; 16];
+        let mask = [0, 255, 0, 1];
+        let output = composite_with_mask(&original, &edited, &mask).unwrap();
+        assert_eq!(&output[0..4], &[1; 4]);
+        assert_eq!(&output[4..8], &[9; 4]);
+        assert_eq!(&output[8..12], &[1; 4]);
+        assert_eq!(&output[12..16], &[9; 4]);
+        assert!(composite_with_mask(&original, &edited[..12], &mask).is_err());
+        assert!(composite_with_mask(&original, &edited, &mask[..3]).is_err());
+    }
+
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|index| {
+                let value = ((index % width) * 255 / width.max(1)) as u8;
+                [value, value, value, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn psnr_is_none_for_identical_images_and_finite_otherwise() {
+        let before = gradient(16, 16);
+        assert_eq!(psnr(&before, &before), None);
+        let after: Vec<u8> = before.iter().map(|value| value.saturating_add(4)).collect();
+        // 每个通道误差约为 4，PSNR 约为 36 dB
+        let value = psnr(&before, &after).unwrap();
+        assert!((value - 36.1).abs() < 0.5, "psnr = {value}");
+        assert_eq!(psnr(&before, &after[..8]), None);
+    }
+
+    #[test]
+    fn ssim_is_one_for_identical_images_and_drops_with_noise() {
+        let before = gradient(16, 16);
+        assert!((ssim(&before, &before, 16, 16) - 1.0).abs() < 1e-9);
+        let inverted: Vec<u8> = before
+            .chunks_exact(BYTES_PER_PIXEL)
+            .flat_map(|pixel| [255 - pixel[0], 255 - pixel[1], 255 - pixel[2], pixel[3]])
+            .collect();
+        assert!(ssim(&before, &inverted, 16, 16) < 0.5);
+        assert_eq!(ssim(&before, &inverted, 8, 8), 0.0);
+    }
+
+    #[test]
+    fn changed_pixel_ratio_ignores_small_differences() {
+        let before = vec![100u8; 4 * 4];
+        let mut after = before.clone();
+        after[0] = 110;
+        after[4] = 200;
+        assert_eq!(changed_pixel_ratio(&before, &after, 16), 0.25);
+    }
+
+    fn gif_with_frames(frames: usize) -> Vec<u8> {
+        // 1x1 画布，全局颜色表 2 色
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&[1, 0, 1, 0, 0x80, 0, 0]);
+        gif.extend_from_slice(&[0; 6]);
+        for _ in 0..frames {
+            // 图形控制扩展
+            gif.extend_from_slice(&[0x21, 0xF9, 4, 0, 10, 0, 0, 0]);
+            gif.extend_from_slice(&[0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0]);
+            gif.extend_from_slice(&[2, 2, 0x4C, 0x01, 0]);
+        }
+        gif.push(0x3B);
+        gif
+    }
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn riff_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    #[test]
+    fn count_frames_reads_gif_image_descriptors() {
+        assert_eq!(count_frames(&gif_with_frames(1), ImageFormat::Gif), 1);
+        assert_eq!(count_frames(&gif_with_frames(3), ImageFormat::Gif), 3);
+        // 截断在第二帧中间时只统计已读到的帧
+        let gif = gif_with_frames(2);
+        assert_eq!(count_frames(&gif[..gif.len() - 4], ImageFormat::Gif), 2);
+        assert_eq!(count_frames(&gif[..10], ImageFormat::Gif), 1);
+    }
+
+    #[test]
+    fn count_frames_reads_apng_actl() {
+        let signature = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        let mut png = signature.to_vec();
+        png.extend(png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+        let plain = [png.clone(), png_chunk(b"IDAT", &[0]), png_chunk(b"IEND", &[])].concat();
+        assert_eq!(count_frames(&plain, ImageFormat::Png), 1);
+
+        png.extend(png_chunk(b"acTL", &[0, 0, 0, 4, 0, 0, 0, 0]));
+        png.extend(png_chunk(b"IDAT", &[0]));
+        assert_eq!(count_frames(&png, ImageFormat::Png), 4);
+    }
+
+    #[test]
+    fn count_frames_reads_webp_anmf_chunks() {
+        let body = [
+            b"WEBP".to_vec(),
+            riff_chunk(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            riff_chunk(b"ANIM", &[0; 6]),
+            riff_chunk(b"ANMF", &[0; 3]),
+            riff_chunk(b"ANMF", &[0; 4]),
+        ]
+        .concat();
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        webp.extend(body);
+        assert_eq!(count_frames(&webp, ImageFormat::WebP), 2);
+        assert_eq!(count_frames(&webp[..12], ImageFormat::WebP), 1);
+    }
+}
@@ -1,6 +1,9 @@
 pub mod image_processing;
 pub mod cache;
+pub mod exif;
 pub mod mcp_server;
+pub mod metadata_strip;
 pub mod modelscope;
 pub mod tools;
+pub mod types;
 pub mod web_pages;
@@ -1,16 +1,20 @@
 use std::sync::Arc;
 
 use rmcp::{
-    ErrorData as McpError, ServerHandler,
+    ErrorData as McpError, RoleServer, ServerHandler,
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::{CallToolResult, ServerCapabilities, ServerInfo},
+    service::RequestContext,
     tool, tool_handler, tool_router,
 };
 
 use crate::cache::LocalFileStorage;
 use crate::tools::{
-    CropImageRequest, EditImageRequest, FetchImageRequest, GenerateImageRequest,
-    LocateObjectRequest, OcrExtractRequest, RotateImageRequest,
+    AskImageRequest, CompareImagesRequest, CountObjectsOutput, CountObjectsRequest,
+    CropImageRequest, EditImageRequest, ExtractObjectsOutput, ExtractObjectsRequest,
+    FetchImageRequest, GenerateImageRequest, LocateObjectOutput, LocateObjectRequest,
+    OcrExtractRequest, PointObjectOutput, PointObjectRequest, RedactImageRequest,
+    RotateImageRequest, SpatialRelationsOutput, SpatialRelationsRequest, StripMetadataRequest,
 };
 
 #[derive(Clone)]
@@ -31,7 +35,7 @@ impl ImageEditorServer {
 #[tool_router]
 impl ImageEditorServer {
     #[tool(
-        description = "从URL列表获取图像并返回图像资源数组，如果用户问起为什么不能直接处理聊天界面上传的图片，就提醒用户必须提供图片的url才能处理。使用![](url)是方式展现图片"
+        description = "从URL列表获取图像并返回图像资源数组，包含格式、尺寸、颜色类型、位深、帧数、EXIF 等技术信息（describe=false 时跳过模型描述），如果用户问起为什么不能直接处理聊天界面上传的图片，就提醒用户必须提供图片的url才能处理。使用![](url)是方式展现图片"
     )]
     async fn fetch_image(
         &self,
@@ -57,7 +61,7 @@ impl ImageEditorServer {
     }
 
     #[tool(
-        description = "OCR文字提取（支持URL列表并发），提取完成后需要使用![](url)是方式展现图片"
+        description = "OCR文字提取（支持URL列表并发），output 可选 markdown、plain、json_layout（返回逐行文字与 0-999 边界框）、tables（返回表格二维数组与 CSV），可用 region 只识别局部区域、language 提示文字语言，提取完成后需要使用![](url)是方式展现图片"
     )]
     async fn ocr_extract(
         &self,
@@ -67,31 +71,125 @@ impl ImageEditorServer {
     }
 
     #[tool(
-        description = "定位图像中的指定物体，返回二维边界框坐标，在裁剪时，先使用locate_object定位物体，再使用crop_image裁剪物体"
+        description = "定位图像中的指定物体，以 JSON 返回每个目标的 Qwen3 归一化坐标（0-999）、像素坐标、标签与置信度，annotate 为 true 时额外返回绘制了边界框与序号的标注图（使用![](url)是方式展现），需要裁剪出所有目标时直接使用extract_objects",
+        output_schema = rmcp::handler::server::tool::schema_for_output::<LocateObjectOutput>()
+            .expect("LocateObjectOutput schema must be an object")
     )]
     async fn locate_object(
         &self,
         Parameters(request): Parameters<LocateObjectRequest>,
     ) -> Result<CallToolResult, McpError> {
-        crate::tools::locate_object(Parameters(request)).await
+        crate::tools::locate_object(&self.storage, Parameters(request)).await
     }
 
     #[tool(
-        description = "AI生成图像，支持 aspect_ratio（1:1、16:9、9:16、4:3、3:4、3:2、2:3）与 resolution（1k、2k、4k），调用前提醒用户可能耗时较长，使用![](url)是方式展现图片"
+        description = "以点的形式定位图像中的指定物体或界面元素（适合需要点击坐标的 UI 自动化），返回每个点的 Qwen3 归一化坐标（0-999）与像素坐标",
+        output_schema = rmcp::handler::server::tool::schema_for_output::<PointObjectOutput>()
+            .expect("PointObjectOutput schema must be an object")
+    )]
+    async fn point_object(
+        &self,
+        Parameters(request): Parameters<PointObjectRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::point_object(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "定位并裁剪出图像中所有指定物体，margin 可按边界框比例向外扩展，返回每个裁剪结果的缓存地址与其在原图中的坐标，使用![](url)是方式展现图片",
+        output_schema = rmcp::handler::server::tool::schema_for_output::<ExtractObjectsOutput>()
+            .expect("ExtractObjectsOutput schema must be an object")
+    )]
+    async fn extract_objects(
+        &self,
+        Parameters(request): Parameters<ExtractObjectsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::extract_objects(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "统计图像中指定物体的数量：对模型返回的边界框做非极大值抑制去重与最小尺寸过滤，返回数量、去重后的边界框，annotate 为 true 时额外返回标注图（使用![](url)是方式展现）",
+        output_schema = rmcp::handler::server::tool::schema_for_output::<CountObjectsOutput>()
+            .expect("CountObjectsOutput schema must be an object")
+    )]
+    async fn count_objects(
+        &self,
+        Parameters(request): Parameters<CountObjectsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::count_objects(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "隐私打码：对 boxes 指定的区域或 object 描述的物体（如人脸、车牌、证件号码，由视觉模型定位）进行高斯模糊、马赛克或纯色填充，只缓存打码后的图片，使用![](url)是方式展现图片"
+    )]
+    async fn redact_image(
+        &self,
+        Parameters(request): Parameters<RedactImageRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::redact_image(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "去除图片中的 EXIF（含 GPS 位置）、XMP、IPTC、注释等元数据，带方向信息的图片会先按方向摆正像素，返回处理后图片的 URL，使用![](url)是方式展现图片"
+    )]
+    async fn strip_metadata(
+        &self,
+        Parameters(request): Parameters<StripMetadataRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::strip_metadata(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "分析图像中多个物体（2-6 个）的相对位置：定位每个物体后在本地计算左右、上下、重叠与 IoU、包含、距离和面积比，以节点与边的关系图返回",
+        output_schema = rmcp::handler::server::tool::schema_for_output::<SpatialRelationsOutput>()
+            .expect("SpatialRelationsOutput schema must be an object")
+    )]
+    async fn spatial_relations(
+        &self,
+        Parameters(request): Parameters<SpatialRelationsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::spatial_relations(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "针对一张或多张图片提问（视觉问答），返回模型回答；提供 json_schema 时返回符合该结构的 JSON，可直接提取发票金额等结构化字段"
+    )]
+    async fn ask_image(
+        &self,
+        Parameters(request): Parameters<AskImageRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::ask_image(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "对比修改前后两张图片：返回尺寸/格式差异、PSNR、SSIM、变化像素占比与差异热力图（越红差异越大），semantic 为 true 时额外由视觉模型描述内容差异，使用![](url)是方式展现差异图"
+    )]
+    async fn compare_images(
+        &self,
+        Parameters(request): Parameters<CompareImagesRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        crate::tools::compare_images(&self.storage, Parameters(request)).await
+    }
+
+    #[tool(
+        description = "AI生成图像，支持 aspect_ratio（1:1、16:9、9:16、4:3、3:4、3:2、2:3）与 resolution（1k、2k、4k），n 可一次生成多张并以数组返回，指定 seed 并开启 use_cache 可复用相同参数的历史结果，调用前提醒用户可能耗时较长，使用![](url)是方式展现图片"
     )]
     async fn generate_image(
         &self,
         Parameters(request): Parameters<GenerateImageRequest>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
-        crate::tools::generate_image(&self.storage, Parameters(request)).await
+        crate::tools::generate_image(&self.storage, Parameters(request), context).await
     }
 
-    #[tool(description = "AI编辑图像，使用![](url)是方式展现图片，调用前提醒用户可能耗时较长")]
+    #[tool(
+        description = "AI编辑图像，支持通过 image_urls 传入最多 3 张图片进行多图参考编辑（如“把图2中的商品放进图1的场景”）；提供 mask_url 或 mask_box（0-999 坐标）时只重绘该区域，其余像素保持不变，使用![](url)是方式展现图片，调用前提醒用户可能耗时较长"
+    )]
     async fn edit_image(
         &self,
         Parameters(request): Parameters<EditImageRequest>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
-        crate::tools::edit_image(&self.storage, Parameters(request)).await
+        crate::tools::edit_image(&self.storage, Parameters(request), context).await
     }
 
     // #[tool(description = "查看AI生成/编辑图片历史记录")]
@@ -0,0 +1,597 @@
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use image::{
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, codecs::jpeg::JpegEncoder,
+    metadata::Orientation,
+};
+
+use crate::image_processing;
+
+/// 摆正方向后重新编码 JPEG 时使用的质量
+const REENCODE_JPEG_QUALITY: u8 = 92;
+const XMP_APP1_PREFIXES: [&[u8]; 2] = [
+    b"http://ns.adobe.com/xap/1.0/\0",
+    b"http://ns.adobe.com/xmp/extension/\0",
+];
+/// GIF 应用扩展中 XMP 的块长度与应用标识
+const GIF_XMP_APPLICATION: &[u8] = b"\x0bXMP DataXMP";
+
+/// 元数据处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataPolicy {
+    /// 原样保存
+    Keep,
+    /// 去除 EXIF / XMP / IPTC / 注释等元数据，方向写入像素
+    Strip,
+}
+
+impl MetadataPolicy {
+    /// 从环境变量读取策略：`keep` 原样保存，其他值或未配置时为 `strip`
+    fn from_env(name: &str) -> Self {
+        match std::env::var(name) {
+            Ok(value) if value.trim().eq_ignore_ascii_case("keep") => MetadataPolicy::Keep,
+            _ => MetadataPolicy::Strip,
+        }
+    }
+
+    /// `UPLOAD_METADATA_POLICY`：上传页面保存的图片
+    pub fn for_uploads() -> Self {
+        Self::from_env("UPLOAD_METADATA_POLICY")
+    }
+
+    /// `OUTPUT_METADATA_POLICY`：缓存中保存的 AI 生成 / 编辑结果
+    pub fn for_outputs() -> Self {
+        Self::from_env("OUTPUT_METADATA_POLICY")
+    }
+}
+
+pub struct StrippedImage {
+    pub bytes: Vec<u8>,
+    /// 输出图片的 MIME 类型，WebP 摆正后会转为 PNG 或 JPEG
+    pub mime_type: String,
+    /// 被去除的元数据类型，如 exif、xmp、iptc、comment、text
+    pub removed: Vec<&'static str>,
+    /// EXIF 方向不为正常方向时，已按方向旋转像素并重新编码
+    pub orientation_baked: bool,
+}
+
+/// 去除图片中的 EXIF（含 GPS）、XMP、IPTC、注释与文本块，保留 ICC 色彩配置。
+/// JPEG / PNG / WebP 在不需要摆正时按块删除、不重新编码；带方向信息的图片先摆正再重新编码，
+/// 此时所有元数据（含 ICC）都不会保留。image 只能以无损方式编码 WebP，体积会成倍增加，
+/// 因此 WebP 摆正后带透明通道的转为 PNG，其余转为 JPEG。GIF 删除 XMP 应用扩展与注释扩展；
+/// BMP 不含这些元数据，原样返回。
+pub fn strip_metadata(bytes: &[u8], mime_type: &str) -> Result<StrippedImage> {
+    let format = image_processing::mime_to_format(mime_type)?;
+    let (stripped, mut removed) = match format {
+        ImageFormat::Jpeg => strip_jpeg(bytes)?,
+        ImageFormat::Png => strip_png(bytes)?,
+        ImageFormat::WebP => strip_webp(bytes)?,
+        ImageFormat::Gif => strip_gif(bytes)?,
+        _ => (bytes.to_vec(), Vec::new()),
+    };
+    removed.sort_unstable();
+    removed.dedup();
+
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(|err| anyhow!("decode image failed: {err}"))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    if orientation == Orientation::NoTransforms {
+        return Ok(StrippedImage {
+            bytes: stripped,
+            mime_type: mime_type.to_string(),
+            removed,
+            orientation_baked: false,
+        });
+    }
+
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|err| anyhow!("decode image failed: {err}"))?;
+    image.apply_orientation(orientation);
+    let output_format = match format {
+        ImageFormat::WebP if image.color().has_alpha() => ImageFormat::Png,
+        ImageFormat::WebP => ImageFormat::Jpeg,
+        format => format,
+    };
+    let mut output = Vec::new();
+    match output_format {
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut output, REENCODE_JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .map_err(|err| anyhow!("encode image failed: {err}"))?,
+        _ => image
+            .write_to(&mut Cursor::new(&mut output), output_format)
+            .map_err(|err| anyhow!("encode image failed: {err}"))?,
+    }
+    if !removed.contains(&"exif") {
+        removed.push("exif");
+        removed.sort_unstable();
+    }
+    Ok(StrippedImage {
+        bytes: output,
+        mime_type: output_format.to_mime_type().to_string(),
+        removed,
+        orientation_baked: true,
+    })
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Result<(Vec<u8>, Vec<&'static str>)> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return Err(anyhow!("invalid jpeg"));
+    }
+    let mut output = Vec::with_capacity(bytes.len());
+    output.extend_from_slice(&bytes[..2]);
+    let mut removed = Vec::new();
+    let mut has_mpf = false;
+    let mut position = 2;
+    loop {
+        if position >= bytes.len() {
+            return Err(anyhow!("truncated jpeg"));
+        }
+        if bytes[position] != 0xFF {
+            return Err(anyhow!("invalid jpeg marker at {position}"));
+        }
+        let mut marker_position = position + 1;
+        while bytes.get(marker_position) == Some(&0xFF) {
+            marker_position += 1;
+        }
+        let marker = *bytes
+            .get(marker_position)
+            .ok_or_else(|| anyhow!("truncated jpeg"))?;
+        // 扫描数据开始后不再有元数据段，其余部分原样保留；
+        // 含 MPF 时第一个 EOI 之后是附带的其他图片（如深度图、原图），可能带有完整 EXIF，一并丢弃
+        if marker == 0xDA || marker == 0xD9 {
+            let end = if has_mpf {
+                find_jpeg_eoi(bytes, position).ok_or_else(|| anyhow!("truncated jpeg"))?
+            } else {
+                bytes.len()
+            };
+            output.extend_from_slice(&bytes[position..end]);
+            break;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            output.extend_from_slice(&bytes[position..=marker_position]);
+            position = marker_position + 1;
+            continue;
+        }
+        let length = bytes
+            .get(marker_position + 1..marker_position + 3)
+            .map(|length| u16::from_be_bytes([length[0], length[1]]) as usize)
+            .filter(|length| *length >= 2)
+            .ok_or_else(|| anyhow!("truncated jpeg segment"))?;
+        let end = marker_position + 1 + length;
+        let payload = bytes
+            .get(marker_position + 3..end)
+            .ok_or_else(|| anyhow!("truncated jpeg segment"))?;
+        let kind = match marker {
+            0xE1 if payload.starts_with(b"Exif\0") => Some("exif"),
+            0xE1 if XMP_APP1_PREFIXES
+                .iter()
+                .any(|prefix| payload.starts_with(prefix)) =>
+            {
+                Some("xmp")
+            }
+            0xE2 if payload.starts_with(b"MPF\0") => {
+                has_mpf = true;
+                Some("mpf")
+            }
+            0xED => Some("iptc"),
+            0xFE => Some("comment"),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => removed.push(kind),
+            None => output.extend_from_slice(&bytes[position..end]),
+        }
+        position = end;
+    }
+    Ok((output, removed))
+}
+
+/// 从 `start` 起第一个 EOI 标记之后的位置；压缩数据中的 0xFF 会以 0xFF00 转义，不会误判
+fn find_jpeg_eoi(bytes: &[u8], start: usize) -> Option<usize> {
+    bytes[start..]
+        .windows(2)
+        .position(|window| window == [0xFF, 0xD9])
+        .map(|offset| start + offset + 2)
+}
+
+fn strip_png(bytes: &[u8]) -> Result<(Vec<u8>, Vec<&'static str>)> {
+    const SIGNATURE_LEN: usize = 8;
+    if bytes.len() < SIGNATURE_LEN {
+        return Err(anyhow!("invalid png"));
+    }
+    let mut output = Vec::with_capacity(bytes.len());
+    output.extend_from_slice(&bytes[..SIGNATURE_LEN]);
+    let mut removed = Vec::new();
+    let mut position = SIGNATURE_LEN;
+    while position < bytes.len() {
+        let header = bytes
+            .get(position..position + 8)
+            .ok_or_else(|| anyhow!("truncated png chunk"))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+        // 长度 + 类型 + 数据 + CRC
+        let end = position + 12 + length;
+        let data = bytes
+            .get(position + 8..position + 8 + length)
+            .filter(|_| end <= bytes.len())
+            .ok_or_else(|| anyhow!("truncated png chunk"))?;
+        let kind = match chunk_type {
+            b"eXIf" => Some("exif"),
+            b"iTXt" if data.starts_with(b"XML:com.adobe.xmp\0") => Some("xmp"),
+            b"tEXt" | b"zTXt" | b"iTXt" => Some("text"),
+            b"tIME" => Some("time"),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => removed.push(kind),
+            None => output.extend_from_slice(&bytes[position..end]),
+        }
+        position = end;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+    Ok((output, removed))
+}
+
+fn strip_webp(bytes: &[u8]) -> Result<(Vec<u8>, Vec<&'static str>)> {
+    const VP8X_EXIF_FLAG: u8 = 0x08;
+    const VP8X_XMP_FLAG: u8 = 0x04;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return Err(anyhow!("invalid webp"));
+    }
+    let mut output = Vec::with_capacity(bytes.len());
+    output.extend_from_slice(&bytes[..12]);
+    let mut removed = Vec::new();
+    let mut position = 12;
+    while position + 8 <= bytes.len() {
+        let fourcc = &bytes[position..position + 4];
+        let size = u32::from_le_bytes([
+            bytes[position + 4],
+            bytes[position + 5],
+            bytes[position + 6],
+            bytes[position + 7],
+        ]) as usize;
+        // 块数据按偶数字节对齐
+        let end = (position + 8 + size + (size & 1)).min(bytes.len());
+        if position + 8 + size > bytes.len() {
+            return Err(anyhow!("truncated webp chunk"));
+        }
+        match fourcc {
+            b"EXIF" => removed.push("exif"),
+            b"XMP " => removed.push("xmp"),
+            _ => output.extend_from_slice(&bytes[position..end]),
+        }
+        position = end;
+    }
+    if !removed.is_empty() {
+        // VP8X 中声明了 EXIF / XMP 的标志位需要一并清除
+        if output.get(12..16) == Some(b"VP8X".as_slice()) && output.len() > 20 {
+            output[20] &= !(VP8X_EXIF_FLAG | VP8X_XMP_FLAG);
+        }
+        let riff_size = (output.len() - 8) as u32;
+        output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    }
+    Ok((output, removed))
+}
+
+/// GIF 的 XMP 存放在 `XMP DataXMP` 应用扩展中，注释存放在注释扩展中；
+/// 图形控制、NETSCAPE 循环等其他扩展与图像数据原样保留
+fn strip_gif(bytes: &[u8]) -> Result<(Vec<u8>, Vec<&'static str>)> {
+    if bytes.len() < 13 || !bytes.starts_with(b"GIF8") {
+        return Err(anyhow!("invalid gif"));
+    }
+    // 文件头 6 字节 + 逻辑屏幕描述符 7 字节 + 全局颜色表
+    let header_end = 13 + image_processing::color_table_len(bytes[10]);
+    let mut output = bytes
+        .get(..header_end)
+        .ok_or_else(|| anyhow!("invalid gif"))?
+        .to_vec();
+    let mut removed = Vec::new();
+    let mut position = header_end;
+    loop {
+        let end = match bytes.get(position) {
+            Some(0x21) => image_processing::skip_gif_sub_blocks(bytes, position + 2),
+            Some(0x2C) => bytes.get(position + 9).and_then(|&flags| {
+                // 描述符 10 字节 + 局部颜色表 + LZW 最小码长 1 字节
+                let data_start = position + 10 + image_processing::color_table_len(flags) + 1;
+                image_processing::skip_gif_sub_blocks(bytes, data_start)
+            }),
+            Some(0x3B) => {
+                output.push(0x3B);
+                break;
+            }
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("truncated gif block"))?;
+        let block = &bytes[position..end];
+        let kind = match block {
+            [0x21, 0xFE, ..] => Some("comment"),
+            [0x21, 0xFF, rest @ ..] if rest.starts_with(GIF_XMP_APPLICATION) => Some("xmp"),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => removed.push(kind),
+            None => output.extend_from_slice(block),
+        }
+        position = end;
+    }
+    Ok((output, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{
+        ExtendedColorType, ImageEncoder,
+        codecs::{gif::GifEncoder, webp::WebPEncoder},
+    };
+
+    use super::*;
+
+    /// 只含 IFD0 的 EXIF，`orientation` 为 None 时不写方向
+    fn exif_tiff(orientation: Option<u16>) -> Vec<u8> {
+        let mut tiff = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0];
+        match orientation {
+            Some(orientation) => {
+                tiff.extend_from_slice(&[1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+                tiff.extend_from_slice(&orientation.to_le_bytes());
+                tiff.extend_from_slice(&[0, 0]);
+            }
+            None => tiff.extend_from_slice(&[0, 0]),
+        }
+        tiff.extend_from_slice(&[0; 4]);
+        tiff
+    }
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn encode_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let pixels = vec![128u8; (width * height * 3) as usize];
+        let mut output = Vec::new();
+        JpegEncoder::new(&mut output)
+            .encode(&pixels, width, height, ExtendedColorType::Rgb8)
+            .unwrap();
+        output
+    }
+
+    /// 在 SOI 之后插入元数据段
+    fn jpeg_with_segments(segments: &[Vec<u8>]) -> Vec<u8> {
+        let base = encode_jpeg(4, 2);
+        [&base[..2], &segments.concat(), &base[2..]].concat()
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32(&[kind, data].concat()).to_be_bytes());
+        chunk
+    }
+
+    /// 不压缩的 zlib 数据流（单个 stored 块）
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let mut output = vec![0x78, 0x01, 0x01];
+        output.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        output.extend_from_slice(&(!(data.len() as u16)).to_le_bytes());
+        output.extend_from_slice(data);
+        let (mut a, mut b) = (1u32, 0u32);
+        for byte in data {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        output.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        output
+    }
+
+    fn riff_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// 扩展格式 WebP：VP8X + ICCP + 无损图像数据 + 可选的 EXIF / XMP
+    fn extended_webp(width: u32, height: u32, exif: Option<&[u8]>, xmp: bool) -> Vec<u8> {
+        let pixels = vec![200u8; (width * height * 3) as usize];
+        let mut simple = Vec::new();
+        WebPEncoder::new_lossless(&mut simple)
+            .write_image(&pixels, width, height, ExtendedColorType::Rgb8)
+            .unwrap();
+        // 简单格式只有一个 VP8L 块
+        let image_chunk = &simple[12..];
+
+        let mut flags = 0x20;
+        if exif.is_some() {
+            flags |= 0x08;
+        }
+        if xmp {
+            flags |= 0x04;
+        }
+        let mut vp8x = vec![flags, 0, 0, 0];
+        vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        let mut body = b"WEBP".to_vec();
+        body.extend(riff_chunk(b"VP8X", &vp8x));
+        body.extend(riff_chunk(b"ICCP", b"fake icc profile!"));
+        body.extend_from_slice(image_chunk);
+        if let Some(exif) = exif {
+            body.extend(riff_chunk(b"EXIF", exif));
+        }
+        if xmp {
+            body.extend(riff_chunk(b"XMP ", b"<x:xmpmeta/>"));
+        }
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        webp.extend(body);
+        webp
+    }
+
+    fn encode_gif(width: u32, height: u32) -> Vec<u8> {
+        let pixels = vec![200u8; (width * height * 4) as usize];
+        let mut output = Vec::new();
+        GifEncoder::new(&mut output)
+            .encode(&pixels, width, height, ExtendedColorType::Rgba8)
+            .unwrap();
+        output
+    }
+
+    /// 以单个子块组成的 GIF 扩展块
+    fn gif_extension(label: u8, head: &[u8], data: &[u8]) -> Vec<u8> {
+        [&[0x21, label], head, &[data.len() as u8], data, &[0]].concat()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn strip_jpeg_removes_exif_xmp_and_comment_but_keeps_icc() {
+        let icc = jpeg_segment(0xE2, b"ICC_PROFILE\0\x01\x01fake icc");
+        let jpeg = jpeg_with_segments(&[
+            jpeg_segment(0xE1, &[b"Exif\0\0".as_slice(), &exif_tiff(None)].concat()),
+            jpeg_segment(0xE1, &[XMP_APP1_PREFIXES[0], b"<x:xmpmeta/>"].concat()),
+            icc.clone(),
+            jpeg_segment(0xFE, b"secret comment"),
+        ]);
+        let stripped = strip_metadata(&jpeg, "image/jpeg").unwrap();
+        assert_eq!(stripped.removed, ["comment", "exif", "xmp"]);
+        assert_eq!(stripped.mime_type, "image/jpeg");
+        assert!(!stripped.orientation_baked);
+        assert!(!contains(&stripped.bytes, b"Exif\0"));
+        assert!(!contains(&stripped.bytes, b"xmpmeta"));
+        assert!(!contains(&stripped.bytes, b"secret comment"));
+        assert_eq!(stripped.bytes, jpeg_with_segments(&[icc]));
+        image_processing::decode_image(&stripped.bytes, "image/jpeg").unwrap();
+    }
+
+    #[test]
+    fn strip_jpeg_drops_mpf_embedded_images() {
+        let embedded = [
+            &[0xFF, 0xD8][..],
+            &jpeg_segment(0xE1, &[b"Exif\0\0".as_slice(), &exif_tiff(None)].concat()),
+            &encode_jpeg(2, 2)[2..],
+        ]
+        .concat();
+        let main = jpeg_with_segments(&[jpeg_segment(0xE2, b"MPF\0II*\0")]);
+        let stripped = strip_metadata(&[main, embedded].concat(), "image/jpeg").unwrap();
+        assert_eq!(stripped.removed, ["mpf"]);
+        assert_eq!(stripped.bytes, jpeg_with_segments(&[]));
+        assert!(!contains(&stripped.bytes, b"Exif\0"));
+    }
+
+    #[test]
+    fn strip_png_removes_text_chunks_but_keeps_icc() {
+        let base = image_processing::encode_png(&[255; 4 * 6], 3, 2).unwrap();
+        // 签名 8 字节 + IHDR 25 字节
+        let (head, tail) = base.split_at(33);
+        let mut icc = b"icc\0\0".to_vec();
+        icc.extend(zlib_stored(b"fake icc profile"));
+        let png = [
+            head.to_vec(),
+            png_chunk(b"iCCP", &icc),
+            png_chunk(b"eXIf", &exif_tiff(None)),
+            png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x:xmpmeta/>"),
+            png_chunk(b"tEXt", b"Comment\0secret"),
+            tail.to_vec(),
+        ]
+        .concat();
+        let stripped = strip_metadata(&png, "image/png").unwrap();
+        assert_eq!(stripped.removed, ["exif", "text", "xmp"]);
+        assert_eq!(stripped.bytes.len(), base.len() + 12 + icc.len());
+        assert!(contains(&stripped.bytes, b"iCCP"));
+        assert!(!contains(&stripped.bytes, b"eXIf"));
+        assert!(!contains(&stripped.bytes, b"tEXt"));
+        image_processing::decode_image(&stripped.bytes, "image/png").unwrap();
+    }
+
+    #[test]
+    fn strip_webp_fixes_riff_size_and_vp8x_flags() {
+        let webp = extended_webp(3, 2, Some(&exif_tiff(None)), true);
+        let stripped = strip_metadata(&webp, "image/webp").unwrap();
+        assert_eq!(stripped.removed, ["exif", "xmp"]);
+        let bytes = &stripped.bytes;
+        let riff_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        assert_eq!(riff_size as usize, bytes.len() - 8);
+        assert_eq!(bytes[20], 0x20);
+        assert!(contains(bytes, b"ICCP"));
+        assert!(!contains(bytes, b"EXIF"));
+        assert!(!contains(bytes, b"XMP "));
+        assert_eq!(
+            image_processing::decode_image(bytes, "image/webp").unwrap().1,
+            3
+        );
+    }
+
+    #[test]
+    fn strip_webp_reencodes_rotated_images_as_jpeg() {
+        let webp = extended_webp(3, 2, Some(&exif_tiff(Some(6))), false);
+        let stripped = strip_metadata(&webp, "image/webp").unwrap();
+        assert!(stripped.orientation_baked);
+        assert_eq!(stripped.mime_type, "image/jpeg");
+        let (_, width, height) = image_processing::decode_image(&stripped.bytes, "image/jpeg").unwrap();
+        assert_eq!((width, height), (2, 3));
+    }
+
+    #[test]
+    fn strip_gif_removes_xmp_and_comment_extensions() {
+        let base = encode_gif(2, 2);
+        let header_end = 13 + image_processing::color_table_len(base[10]);
+        let (head, tail) = base.split_at(header_end);
+        let gif = [
+            head.to_vec(),
+            gif_extension(0xFE, &[], b"secret comment"),
+            gif_extension(0xFF, GIF_XMP_APPLICATION, b"<x:xmpmeta/>"),
+            tail.to_vec(),
+        ]
+        .concat();
+        let stripped = strip_metadata(&gif, "image/gif").unwrap();
+        assert_eq!(stripped.removed, ["comment", "xmp"]);
+        assert!(!stripped.orientation_baked);
+        assert_eq!(stripped.bytes, base);
+        image_processing::decode_image(&stripped.bytes, "image/gif").unwrap();
+
+        let plain = strip_metadata(&base, "image/gif").unwrap();
+        assert!(plain.removed.is_empty());
+        assert_eq!(plain.bytes, base);
+    }
+
+    #[test]
+    fn strip_metadata_rejects_truncated_input() {
+        let jpeg = jpeg_with_segments(&[jpeg_segment(
+            0xE1,
+            &[b"Exif\0\0".as_slice(), &exif_tiff(None)].concat(),
+        )]);
+        assert!(strip_metadata(&jpeg[..12], "image/jpeg").is_err());
+        assert!(strip_metadata(&jpeg[..2], "image/jpeg").is_err());
+
+        let png = image_processing::encode_png(&[255; 4], 1, 1).unwrap();
+        assert!(strip_metadata(&png[..20], "image/png").is_err());
+
+        let webp = extended_webp(3, 2, None, false);
+        assert!(strip_metadata(&webp[..webp.len() - 3], "image/webp").is_err());
+
+        let gif = encode_gif(2, 2);
+        assert!(strip_metadata(&gif[..gif.len() - 3], "image/gif").is_err());
+    }
+}
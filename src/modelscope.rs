@@ -1,13 +1,36 @@
+use std::sync::Arc;
+
 use anyhow::{Result, anyhow};
 use reqwest::Client;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tokio::time::{Duration, Instant, sleep};
+use tokio_util::sync::CancellationToken;
+
+pub use crate::types::{DescriptionExtras, DescriptionField, Loras, MAX_LORA_COUNT};
 
 const MODELSCOPE_API_ROOT: &str = "https://api-inference.modelscope.cn";
 const MODELSCOPE_BASE_URL: &str = "https://api-inference.modelscope.cn/v1";
 const MODELSCOPE_MODEL: &str = "Qwen/Qwen3-VL-8B-Instruct";
 const OCR_PROMPT: &str = "qwenvl markdown";
+const OCR_PLAIN_PROMPT: &str = concat!(
+    "提取图中的所有文字，只输出纯文本，保持原有的换行与阅读顺序，",
+    "不要使用任何 Markdown 格式，不要添加解释。"
+);
+const OCR_LAYOUT_PROMPT: &str = concat!(
+    "识别图中的每一行文字，并以JSON数组输出，每个元素包含以下字段：\n",
+    "1. text: 该行文字内容\n",
+    "2. bbox_2d: 该行文字的边界框 [x1, y1, x2, y2]\n",
+    "3. confidence: 识别置信度（0-1 之间的小数）\n\n",
+    "请只返回JSON，不要包含其他文字。"
+);
+const OCR_TABLES_PROMPT: &str = concat!(
+    "识别图中的所有表格，并以JSON数组输出，每个元素代表一个表格，包含以下字段：\n",
+    "1. title: 表格标题（没有则为空字符串）\n",
+    "2. rows: 二维字符串数组，按行列出单元格内容，第一行为表头，合并单元格重复填写\n\n",
+    "没有表格时返回 []。请只返回JSON，不要包含其他文字。"
+);
 const IMAGE_DESCRIPTION_PROMPT: &str = concat!(
     "请分析这张图片，并以JSON格式回复，包含以下字段：\n",
     "1. name: 图片的简短名称（不超过10个字，直接描述主体）\n",
@@ -15,26 +38,60 @@ const IMAGE_DESCRIPTION_PROMPT: &str = concat!(
     "请只返回JSON，不要包含其他文字。示例格式：\n",
     "{\"name\": \"校园动漫场景\", \"description\": \"这是一张...\"}"
 );
+const IMAGE_DIFF_PROMPT: &str = concat!(
+    "图1为修改前的图片，图2为修改后的图片。请逐条列出两张图片在内容上的差异",
+    "（新增、移除、替换的物体，颜色、文字、构图、风格的变化等），忽略压缩噪点等细微像素差异。",
+    "若没有明显差异，请直接说明。"
+);
 
 const DEFAULT_POLL_INTERVAL_MS: u64 = 5_000;
-const DEFAULT_TIMEOUT_MS: u64 = 5 * 60 * 1_000;
-const Z_TURBO_MODEL: &str = "Tongyi-MAI/Z-Image-Turbo";
+pub const DEFAULT_TIMEOUT_MS: u64 = 5 * 60 * 1_000;
+pub const Z_TURBO_MODEL: &str = "Tongyi-MAI/Z-Image-Turbo";
 const QWEN_IMAGE_EDIT_MODEL: &str = "Qwen/Qwen-Image-Edit-2511";
 
-fn build_image_description_prompt(focus: Option<&str>) -> String {
-    match focus {
-        Some(focus) if !focus.trim().is_empty() => {
-            format!(
-                "请分析这张图片，并以JSON格式回复，包含以下字段：\n\
-1. name: 图片的简短名称（不超过10个字，直接描述主体）\n\
-2. description: 图片的详细描述（包括主要对象、场景、颜色、氛围等）\n\n\
-【特别关注】：{}\n\n\
-请只返回JSON，不要包含其他文字。",
-                focus
-            )
-        }
-        _ => IMAGE_DESCRIPTION_PROMPT.to_string(),
+fn build_image_description_prompt(options: &DescriptionOptions) -> String {
+    let focus = options
+        .focus
+        .as_deref()
+        .map(str::trim)
+        .filter(|focus| !focus.is_empty());
+    let language = options
+        .language
+        .as_deref()
+        .map(str::trim)
+        .filter(|language| !language.is_empty());
+    if focus.is_none()
+        && language.is_none()
+        && options.detail == DescriptionDetail::Normal
+        && options.extra_fields.is_empty()
+    {
+        return IMAGE_DESCRIPTION_PROMPT.to_string();
     }
+
+    let mut fields = vec![
+        "name: 图片的简短名称（不超过10个字，直接描述主体）".to_string(),
+        format!("description: {}", options.detail.instruction()),
+    ];
+    fields.extend(
+        options
+            .extra_fields
+            .iter()
+            .map(|field| field.instruction().to_string()),
+    );
+    let mut prompt = "请分析这张图片，并以JSON格式回复，包含以下字段：\n".to_string();
+    for (index, field) in fields.iter().enumerate() {
+        prompt.push_str(&format!("{}. {}\n", index + 1, field));
+    }
+    if let Some(focus) = focus {
+        prompt.push_str(&format!("\n【特别关注】：{focus}\n"));
+    }
+    if let Some(language) = language {
+        prompt.push_str(&format!(
+            "\n所有文字内容（name、description 及标签、物体名称等）请使用以下语言撰写：{language}\n"
+        ));
+    }
+    prompt.push_str("\n请只返回JSON，不要包含其他文字。");
+    prompt
 }
 
 async fn assert_ok_response(response: reqwest::Response) -> Result<reqwest::Response> {
@@ -90,23 +147,270 @@ pub struct GenerateImageOptions {
     pub negative_prompt: Option<String>,
     pub size: Option<String>,
     pub steps: Option<u32>,
+    pub seed: Option<u32>,
+    pub guidance: Option<f32>,
+    pub loras: Option<Loras>,
+}
+
+/// 异步任务单次轮询后的状态快照
+#[derive(Debug, Clone)]
+pub struct TaskProgress {
+    pub task_id: String,
+    pub task_status: String,
+    pub poll_count: u32,
+    pub elapsed: Duration,
+    pub timeout: Duration,
+}
+
+/// 轮询异步任务时的取消信号与进度回调
+#[derive(Clone, Default)]
+pub struct TaskObserver {
+    cancellation: CancellationToken,
+    on_progress: Option<Arc<dyn Fn(TaskProgress) + Send + Sync>>,
+}
+
+impl TaskObserver {
+    pub fn new(cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation,
+            on_progress: None,
+        }
+    }
+
+    pub fn with_progress<F>(mut self, on_progress: F) -> Self
+    where
+        F: Fn(TaskProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+
+    fn report(&self, progress: TaskProgress) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(progress);
+        }
+    }
 }
 
 pub struct GenerateImageResult {
-    pub image_url: String,
+    pub image_urls: Vec<String>,
     pub task_id: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// OCR 输出模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrOutputMode {
+    /// Markdown 文本（保留标题、表格等结构）
+    #[default]
+    Markdown,
+    /// 纯文本
+    Plain,
+    /// 逐行文字及其边界框
+    JsonLayout,
+    /// 表格（二维数组与 CSV）
+    Tables,
+}
+
+impl OcrOutputMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrOutputMode::Markdown => "markdown",
+            OcrOutputMode::Plain => "plain",
+            OcrOutputMode::JsonLayout => "json_layout",
+            OcrOutputMode::Tables => "tables",
+        }
+    }
+
+    fn prompt(&self) -> &'static str {
+        match self {
+            OcrOutputMode::Markdown => OCR_PROMPT,
+            OcrOutputMode::Plain => OCR_PLAIN_PROMPT,
+            OcrOutputMode::JsonLayout => OCR_LAYOUT_PROMPT,
+            OcrOutputMode::Tables => OCR_TABLES_PROMPT,
+        }
+    }
+}
+
+/// 带坐标的 OCR 文本块，坐标与 locate_object 一致为 Qwen3 归一化坐标（0-999）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OcrTextBlock {
+    pub text: String,
+    pub bbox: BoundingBox,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// OCR 识别出的表格
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OcrTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub rows: Vec<Vec<String>>,
+    pub csv: String,
+}
+
+/// 图片描述的详略程度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DescriptionDetail {
+    /// 一句话概括
+    Brief,
+    /// 主要对象、场景、颜色与氛围
+    #[default]
+    Normal,
+    /// 逐一说明所有可见内容
+    Exhaustive,
+}
+
+impl DescriptionDetail {
+    fn instruction(&self) -> &'static str {
+        match self {
+            DescriptionDetail::Brief => "图片的一句话概括（只写一个句子，不要分点或换行）",
+            DescriptionDetail::Normal => "图片的详细描述（包括主要对象、场景、颜色、氛围等）",
+            DescriptionDetail::Exhaustive => concat!(
+                "图片的完整详尽描述（逐一说明所有可见对象及其位置、属性与相互关系，",
+                "以及场景、光线、颜色、风格、氛围和图中文字）"
+            ),
+        }
+    }
+}
+
+impl DescriptionField {
+    fn instruction(&self) -> &'static str {
+        match self {
+            DescriptionField::Tags => "tags: 图片的关键词标签（字符串数组，5-10 个）",
+            DescriptionField::DominantObjects => {
+                "dominant_objects: 图中最主要的物体名称（字符串数组，按显著程度排序，最多 5 个）"
+            }
+            DescriptionField::TextPresent => "text_present: 图中是否包含可辨认的文字（true/false）",
+            DescriptionField::Nsfw => {
+                "nsfw: 图片是否包含色情、血腥暴力等不适宜公开展示的内容（true/false）"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DescriptionOptions {
+    pub focus: Option<String>,
+    /// 输出语言（如 English、日本語），为空时使用中文
+    pub language: Option<String>,
+    pub detail: DescriptionDetail,
+    pub extra_fields: Vec<DescriptionField>,
+}
+
+impl DescriptionExtras {
+    fn from_answer(answer: &Value, fields: &[DescriptionField]) -> Self {
+        let strings = |key: &str| {
+            answer.get(key).and_then(Value::as_array).map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let flag = |key: &str| {
+            answer.get(key).and_then(|value| match value {
+                Value::Bool(flag) => Some(*flag),
+                Value::String(text) => match text.trim().to_ascii_lowercase().as_str() {
+                    "true" | "yes" | "是" => Some(true),
+                    "false" | "no" | "否" => Some(false),
+                    _ => None,
+                },
+                _ => None,
+            })
+        };
+        let mut extras = Self::default();
+        for field in fields {
+            match field {
+                DescriptionField::Tags => extras.tags = strings("tags"),
+                DescriptionField::DominantObjects => {
+                    extras.dominant_objects = strings("dominant_objects")
+                }
+                DescriptionField::TextPresent => extras.text_present = flag("text_present"),
+                DescriptionField::Nsfw => extras.nsfw = flag("nsfw"),
+            }
+        }
+        extras
+    }
+}
+
+pub struct ImageDescription {
+    pub name: String,
+    pub description: String,
+    pub extras: DescriptionExtras,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct BoundingBox {
     pub x1: f32,
     pub y1: f32,
     pub x2: f32,
     pub y2: f32,
+    /// 模型给出的目标标签
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// 模型给出的置信度（0-1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+impl BoundingBox {
+    /// 将 0-999 归一化坐标换算为像素矩形 (x, y, width, height)，区域为空时返回 None
+    pub fn to_pixel_rect(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let scale_x = |value: f32| ((value.clamp(0.0, 1000.0) / 1000.0) * width as f32).floor() as u32;
+        let scale_y = |value: f32| ((value.clamp(0.0, 1000.0) / 1000.0) * height as f32).floor() as u32;
+        let (x1, x2) = (scale_x(self.x1.min(self.x2)), scale_x(self.x1.max(self.x2)));
+        let (y1, y2) = (scale_y(self.y1.min(self.y2)), scale_y(self.y1.max(self.y2)));
+        let (x1, x2) = (x1.min(width), x2.min(width));
+        let (y1, y2) = (y1.min(height), y2.min(height));
+        if x2 <= x1 || y2 <= y1 {
+            return None;
+        }
+        Some((x1, y1, x2 - x1, y2 - y1))
+    }
+}
+
+/// 模型返回的点坐标（Qwen3 归一化坐标，0-999）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Point2D {
+    pub x: f32,
+    pub y: f32,
+    /// 模型给出的目标标签
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// 模型给出的置信度（0-1）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
 }
 
-pub async fn extract_image_text_with_qwen(image_url: &str, api_key: &str) -> Result<String> {
+impl Point2D {
+    /// 换算为像素坐标，结果收敛在图片范围内
+    pub fn to_pixel(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = |value: f32, size: u32| {
+            ((value.clamp(0.0, 1000.0) / 1000.0 * size as f32).floor() as u32)
+                .min(size.saturating_sub(1))
+        };
+        (scale(self.x, width), scale(self.y, height))
+    }
+}
+
+pub async fn extract_image_text_with_qwen(
+    image_url: &str,
+    api_key: &str,
+    mode: OcrOutputMode,
+    language: Option<&str>,
+) -> Result<String> {
     let client = Client::new();
+    let prompt = match language {
+        Some(language) if !language.trim().is_empty() => {
+            format!("{}\n\n图中文字的主要语言：{}", mode.prompt(), language.trim())
+        }
+        _ => mode.prompt().to_string(),
+    };
     let response = client
         .post(format!("{MODELSCOPE_BASE_URL}/chat/completions"))
         .bearer_auth(api_key)
@@ -116,7 +420,7 @@ pub async fn extract_image_text_with_qwen(image_url: &str, api_key: &str) -> Res
                 {
                     "role": "user",
                     "content": [
-                        {"type": "text", "text": OCR_PROMPT},
+                        {"type": "text", "text": prompt},
                         {"type": "image_url", "image_url": {"url": image_url}}
                     ]
                 }
@@ -144,10 +448,10 @@ pub async fn extract_image_text_with_qwen(image_url: &str, api_key: &str) -> Res
 pub async fn describe_image_with_qwen(
     image_url: &str,
     api_key: &str,
-    focus: Option<&str>,
-) -> Result<(String, String)> {
+    options: &DescriptionOptions,
+) -> Result<ImageDescription> {
     let client = Client::new();
-    let prompt = build_image_description_prompt(focus);
+    let prompt = build_image_description_prompt(options);
     let response = client
         .post(format!("{MODELSCOPE_BASE_URL}/chat/completions"))
         .bearer_auth(api_key)
@@ -172,7 +476,9 @@ pub async fn describe_image_with_qwen(
     if let Some(error) = payload.error.and_then(|err| err.message) {
         eprintln!(
             "[ERROR] locate_object_with_qwen ModelScope 返回错误: message={}, prompt={}, image_url={}",
-            error, prompt, image_url
+            error,
+            prompt,
+            loggable_image_url(image_url)
         );
         return Err(anyhow!("ModelScope 返回错误: {error}"));
     }
@@ -184,7 +490,7 @@ pub async fn describe_image_with_qwen(
         .ok_or_else(|| anyhow!("ModelScope 未返回图片描述内容"))?;
 
     let raw = content.trim();
-    if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(raw) {
+    if let Ok(parsed) = parse_json_answer(raw) {
         let name = parsed
             .get("name")
             .and_then(|value| value.as_str())
@@ -194,11 +500,117 @@ pub async fn describe_image_with_qwen(
             .and_then(|value| value.as_str())
             .unwrap_or("");
         if !name.is_empty() && !description.is_empty() {
-            return Ok((name.trim().to_string(), description.trim().to_string()));
+            return Ok(ImageDescription {
+                name: name.trim().to_string(),
+                description: description.trim().to_string(),
+                extras: DescriptionExtras::from_answer(&parsed, &options.extra_fields),
+            });
         }
     }
 
-    Ok(("fetched-image".to_string(), raw.to_string()))
+    Ok(ImageDescription {
+        name: "fetched-image".to_string(),
+        description: raw.to_string(),
+        extras: DescriptionExtras::default(),
+    })
+}
+
+/// 以点的形式定位物体，适合需要点击坐标的场景
+pub async fn point_object_with_qwen(
+    image_url: &str,
+    object_name: &str,
+    api_key: &str,
+) -> Result<Vec<Point2D>> {
+    let prompt = format!(
+        "以点的形式定位图中所有{}，并以JSON格式输出，每个元素包含 point_2d（[x, y]，取目标中心或最适合点击的位置）与 label 字段。",
+        object_name
+    );
+    let raw = ask_images_with_qwen(&[image_url.to_string()], &prompt, None, api_key).await?;
+    let cleaned = strip_json_fences(&raw);
+    parse_points(&cleaned).inspect_err(|err| {
+        eprintln!(
+            "[ERROR] point_object_with_qwen 解析点坐标失败: error={}, raw={}, prompt={}, image_url={}",
+            err,
+            raw,
+            prompt,
+            loggable_image_url(image_url)
+        );
+    })
+}
+
+/// 针对一张或多张图片回答自由问题；提供 JSON Schema 时要求模型只输出符合该结构的 JSON
+pub async fn ask_images_with_qwen(
+    image_urls: &[String],
+    question: &str,
+    json_schema: Option<&Value>,
+    api_key: &str,
+) -> Result<String> {
+    let client = Client::new();
+    let prompt = match json_schema {
+        Some(schema) => format!(
+            "{}\n\n请严格按照以下 JSON Schema 输出结果，只返回JSON，不要包含其他文字：\n{}",
+            question.trim(),
+            serde_json::to_string_pretty(schema)?
+        ),
+        None => question.trim().to_string(),
+    };
+    let mut content = Vec::with_capacity(image_urls.len() + 1);
+    for image_url in image_urls {
+        content.push(json!({"type": "image_url", "image_url": {"url": image_url}}));
+    }
+    content.push(json!({"type": "text", "text": prompt}));
+    let response = client
+        .post(format!("{MODELSCOPE_BASE_URL}/chat/completions"))
+        .bearer_auth(api_key)
+        .json(&json!({
+            "model": MODELSCOPE_MODEL,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": content
+                }
+            ],
+            "stream": false
+        }))
+        .send()
+        .await?;
+
+    let response = assert_ok_response(response).await?;
+    let payload: ChatCompletionResponse = response.json().await?;
+    if let Some(error) = payload.error.and_then(|err| err.message) {
+        return Err(anyhow!("ModelScope 返回错误: {error}"));
+    }
+    let content = payload
+        .choices
+        .and_then(|choices| choices.into_iter().next())
+        .and_then(|choice| choice.message)
+        .and_then(|msg| msg.content)
+        .ok_or_else(|| anyhow!("ModelScope 未返回回答内容"))?;
+
+    Ok(content.trim().to_string())
+}
+
+/// 描述修改前后两张图片的语义差异
+pub async fn describe_image_differences_with_qwen(
+    before_url: &str,
+    after_url: &str,
+    api_key: &str,
+    focus: Option<&str>,
+) -> Result<String> {
+    let prompt = match focus {
+        Some(focus) if !focus.trim().is_empty() => {
+            format!("{IMAGE_DIFF_PROMPT}\n\n【特别关注】：{}", focus.trim())
+        }
+        _ => IMAGE_DIFF_PROMPT.to_string(),
+    };
+    let image_urls = [before_url.to_string(), after_url.to_string()];
+    ask_images_with_qwen(&image_urls, &prompt, None, api_key).await
+}
+
+/// 解析模型按 JSON Schema 输出的回答（容忍 ```json 代码块）
+pub fn parse_json_answer(raw: &str) -> Result<Value> {
+    let cleaned = strip_json_fences(raw);
+    serde_json::from_str(&cleaned).map_err(|err| anyhow!("回答不是合法的 JSON: {err}"))
 }
 
 pub async fn locate_object_with_qwen(
@@ -244,106 +656,500 @@ pub async fn locate_object_with_qwen(
 
     let raw = content.trim();
     let cleaned = strip_json_fences(raw);
-    match parse_bounding_boxes(&cleaned) {
+    match parse_bounding_boxes(raw) {
         Ok(boxes) => Ok(boxes),
         Err(err) => {
             eprintln!(
                 "[ERROR] locate_object_with_qwen 解析定位结果失败: error={}, raw={}, cleaned={}, prompt={}, image_url={}",
-                err, raw, cleaned, prompt, image_url
+                err,
+                raw,
+                cleaned,
+                prompt,
+                loggable_image_url(image_url)
             );
             Err(err)
         }
     }
 }
 
+/// 解析 json_layout 模式的 OCR 输出
+pub fn parse_ocr_layout(raw: &str) -> Result<Vec<OcrTextBlock>> {
+    let cleaned = strip_json_fences(raw);
+    let value: Value = serde_json::from_str(&cleaned)
+        .map_err(|err| anyhow!("解析 OCR 布局 JSON 失败: {err}, 原始响应: {raw}"))?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut map) => match ["lines", "blocks", "items"]
+            .iter()
+            .find_map(|key| map.remove(*key))
+        {
+            Some(Value::Array(items)) => items,
+            _ => vec![Value::Object(map)],
+        },
+        _ => Vec::new(),
+    };
+    let blocks = items
+        .iter()
+        .filter_map(|item| {
+            let text = ["text", "content"]
+                .iter()
+                .find_map(|key| item.get(*key).and_then(|value| value.as_str()))?;
+            let bbox = parse_bbox_from_value(item)?;
+            let confidence = item
+                .get("confidence")
+                .and_then(|value| value.as_f64())
+                .map(|value| value as f32);
+            Some(OcrTextBlock {
+                text: text.to_string(),
+                bbox,
+                confidence,
+            })
+        })
+        .collect::<Vec<_>>();
+    if blocks.is_empty() && !cleaned.trim_matches(['[', ']', ' ', '\n']).is_empty() {
+        return Err(anyhow!("未能从 OCR 响应中解析出文本块, 原始响应: {raw}"));
+    }
+    Ok(blocks)
+}
+
+/// 解析 tables 模式的 OCR 输出
+pub fn parse_ocr_tables(raw: &str) -> Result<Vec<OcrTable>> {
+    let cleaned = strip_json_fences(raw);
+    let value: Value = serde_json::from_str(&cleaned)
+        .map_err(|err| anyhow!("解析 OCR 表格 JSON 失败: {err}, 原始响应: {raw}"))?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(mut map) => match map.remove("tables") {
+            Some(Value::Array(items)) => items,
+            _ => vec![Value::Object(map)],
+        },
+        _ => Vec::new(),
+    };
+    let tables = items
+        .iter()
+        .filter_map(|item| {
+            // 兼容直接返回二维数组的情况
+            let (title, rows) = match item {
+                Value::Array(_) => (None, item),
+                _ => (
+                    item.get("title")
+                        .and_then(|value| value.as_str())
+                        .map(str::trim)
+                        .filter(|title| !title.is_empty())
+                        .map(str::to_string),
+                    item.get("rows")?,
+                ),
+            };
+            let rows = rows
+                .as_array()?
+                .iter()
+                .filter_map(|row| {
+                    Some(
+                        row.as_array()?
+                            .iter()
+                            .map(|cell| match cell {
+                                Value::String(text) => text.clone(),
+                                Value::Null => String::new(),
+                                other => other.to_string(),
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let csv = rows_to_csv(&rows);
+            Some(OcrTable { title, rows, csv })
+        })
+        .collect();
+    Ok(tables)
+}
+
+fn rows_to_csv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// data URL 体积很大，日志中只保留 MIME 与长度
+fn loggable_image_url(image_url: &str) -> String {
+    match image_url.strip_prefix("data:") {
+        Some(rest) => {
+            let mime_type = rest.split([';', ',']).next().unwrap_or_default();
+            format!("data:{mime_type};base64,...({} bytes)", image_url.len())
+        }
+        None => image_url.to_string(),
+    }
+}
+
+/// 从模型输出中取出 JSON：支持前后夹杂说明文字的 ```json 代码块，
+/// 没有代码块时提取正文中第一个完整的 JSON 数组或对象
 fn strip_json_fences(raw: &str) -> String {
     let trimmed = raw.trim();
-    if trimmed.starts_with("```") {
-        let trimmed = trimmed
-            .trim_start_matches("```")
-            .trim_start_matches("json")
-            .trim();
-        if let Some(end) = trimmed.rfind("```") {
-            return trimmed[..end].trim().to_string();
+    let body = match trimmed.find("```") {
+        Some(start) => {
+            let after = &trimmed[start + 3..];
+            // 跳过 ```json / ```JSON 等语言标记
+            let tag_len = after
+                .find(|ch: char| !ch.is_ascii_alphanumeric())
+                .unwrap_or(after.len());
+            let after = &after[tag_len..];
+            match after.find("```") {
+                Some(end) => after[..end].trim(),
+                None => after.trim(),
+            }
+        }
+        None => trimmed,
+    };
+    if serde_json::from_str::<Value>(body).is_ok() {
+        return body.to_string();
+    }
+    extract_embedded_json(body)
+        .unwrap_or(body)
+        .to_string()
+}
+
+/// 依次尝试以每个 `[` / `{` 开头、括号配平的片段，返回第一个能解析为 JSON 的片段
+fn extract_embedded_json(text: &str) -> Option<&str> {
+    let starts = text
+        .char_indices()
+        .filter(|(_, ch)| *ch == '[' || *ch == '{')
+        .map(|(start, _)| start);
+    for start in starts {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (offset, ch) in text[start..].char_indices() {
+            if in_string {
+                match ch {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match ch {
+                '"' => in_string = true,
+                '[' | '{' => depth += 1,
+                ']' | '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let candidate = &text[start..start + offset + ch.len_utf8()];
+                        if serde_json::from_str::<Value>(candidate).is_ok() {
+                            return Some(candidate);
+                        }
+                        break;
+                    }
+                }
+                _ => {}
+            }
         }
-        return trimmed.to_string();
     }
-    trimmed.to_string()
+    None
 }
 
+/// 解析定位结果，兼容 JSON（含嵌套数组、字符串数字、各种字段名）与
+/// Qwen 的 `<ref>标签</ref><box>(x1,y1),(x2,y2)</box>` 标记格式
 fn parse_bounding_boxes(raw: &str) -> Result<Vec<BoundingBox>> {
-    let value: Value = serde_json::from_str(raw)
-        .map_err(|err| anyhow!("解析定位结果 JSON 失败: {err}, 原始响应: {raw}"))?;
     let mut boxes = Vec::new();
+    let cleaned = strip_json_fences(raw);
+    if let Ok(value) = serde_json::from_str::<Value>(&cleaned) {
+        collect_bounding_boxes(&value, None, &mut boxes);
+    }
+    if boxes.is_empty() {
+        boxes = parse_box_tags(raw);
+    }
+    if boxes.is_empty() {
+        return Err(NoBoundingBoxes {
+            raw: raw.to_string(),
+        }
+        .into());
+    }
+    Ok(boxes)
+}
+
+/// 模型响应中没有任何边界框，通常表示图中没有目标；与网络、鉴权、API 错误区分开
+#[derive(Debug)]
+pub struct NoBoundingBoxes {
+    pub raw: String,
+}
+
+impl std::fmt::Display for NoBoundingBoxes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "未能从响应中解析出边界框, 原始响应: {}", self.raw)
+    }
+}
+
+impl std::error::Error for NoBoundingBoxes {}
+
+/// 定位结果为“没有目标”时返回空列表，其余错误原样返回
+pub fn boxes_or_empty(result: Result<Vec<BoundingBox>>) -> Result<Vec<BoundingBox>> {
+    match result {
+        Err(err) if err.downcast_ref::<NoBoundingBoxes>().is_some() => Ok(Vec::new()),
+        other => other,
+    }
+}
+
+/// 包裹结果列表的字段名
+const BOX_WRAPPER_KEYS: [&str; 7] = [
+    "objects",
+    "results",
+    "detections",
+    "items",
+    "data",
+    "predictions",
+    "bboxes",
+];
+
+fn collect_bounding_boxes(value: &Value, label: Option<&str>, boxes: &mut Vec<BoundingBox>) {
     match value {
         Value::Array(items) => {
+            if let Some(mut bbox) = bbox_from_coords(items) {
+                bbox.label = label.map(str::to_string);
+                boxes.push(bbox);
+                return;
+            }
             for item in items {
-                if let Some(bbox) = parse_bbox_from_value(&item) {
-                    boxes.push(bbox);
-                }
+                collect_bounding_boxes(item, label, boxes);
             }
         }
         Value::Object(map) => {
-            for key in ["bbox", "bboxes", "boxes", "box"] {
+            let own_label = object_label(map);
+            let label = own_label.as_deref().or(label);
+            for key in ["bbox_2d", "bbox", "box", "boxes", "bounding_box"] {
                 if let Some(value) = map.get(key) {
-                    match value {
-                        Value::Array(items) => {
-                            for item in items {
-                                if let Some(bbox) = parse_bbox_from_value(item) {
-                                    boxes.push(bbox);
-                                }
-                            }
-                        }
-                        _ => {
-                            if let Some(bbox) = parse_bbox_from_value(value) {
-                                boxes.push(bbox);
-                            }
-                        }
+                    let before = boxes.len();
+                    collect_bounding_boxes(value, label, boxes);
+                    let confidence = object_confidence(map);
+                    for bbox in &mut boxes[before..] {
+                        bbox.confidence = bbox.confidence.or(confidence);
                     }
-                    break;
+                    return;
                 }
             }
-            if boxes.is_empty() {
-                if let Some(bbox) = parse_bbox_from_value(&Value::Object(map)) {
-                    boxes.push(bbox);
+            if let Some(mut bbox) = parse_bbox_from_value(value) {
+                bbox.label = bbox.label.or_else(|| label.map(str::to_string));
+                boxes.push(bbox);
+                return;
+            }
+            // 只进入已知的结果列表字段，避免把 {"size": [...]} 之类的数组误认为边界框
+            for key in BOX_WRAPPER_KEYS {
+                if let Some(value) = map.get(key) {
+                    collect_bounding_boxes(value, label, boxes);
                 }
             }
         }
         _ => {}
     }
+}
 
-    if boxes.is_empty() {
-        return Err(anyhow!("未能从响应中解析出边界框"));
+/// `[x1, y1, x2, y2]` 或 `[[x1, y1], [x2, y2]]`
+fn bbox_from_coords(items: &[Value]) -> Option<BoundingBox> {
+    let coords: Vec<f32> = if items.len() == 4 {
+        items.iter().map(number_like).collect::<Option<_>>()?
+    } else if items.len() == 2 {
+        items
+            .iter()
+            .map(|point| match point {
+                Value::Array(point) if point.len() == 2 => {
+                    Some([number_like(&point[0])?, number_like(&point[1])?])
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?
+            .concat()
+    } else {
+        return None;
+    };
+    Some(BoundingBox {
+        x1: coords[0],
+        y1: coords[1],
+        x2: coords[2],
+        y2: coords[3],
+        ..Default::default()
+    })
+}
+
+/// 数字或可解析为数字的字符串（模型偶尔输出 "123"）
+fn number_like(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|number| number as f32),
+        Value::String(text) => text.trim().parse::<f32>().ok(),
+        _ => None,
     }
-    Ok(boxes)
 }
 
-fn parse_bbox_from_value(value: &Value) -> Option<BoundingBox> {
+fn object_label(map: &serde_json::Map<String, Value>) -> Option<String> {
+    ["label", "name", "object", "ref", "category", "class"]
+        .iter()
+        .find_map(|key| map.get(*key).and_then(Value::as_str))
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+}
+
+fn object_confidence(map: &serde_json::Map<String, Value>) -> Option<f32> {
+    ["confidence", "score"]
+        .iter()
+        .find_map(|key| map.get(*key).and_then(number_like))
+}
+
+/// 解析 `<ref>标签</ref><box>(x1,y1),(x2,y2)</box>` 及其 `<|box_start|>` 等特殊 token 写法
+fn parse_box_tags(raw: &str) -> Vec<BoundingBox> {
+    let normalized = raw
+        .replace("<|object_ref_start|>", "<ref>")
+        .replace("<|object_ref_end|>", "</ref>")
+        .replace("<|box_start|>", "<box>")
+        .replace("<|box_end|>", "</box>");
+    let mut boxes = Vec::new();
+    let mut label: Option<String> = None;
+    let mut rest = normalized.as_str();
+    loop {
+        let next_ref = rest.find("<ref>");
+        let next_box = rest.find("<box>");
+        match (next_ref, next_box) {
+            (Some(ref_start), box_start) if box_start.is_none_or(|box_start| ref_start < box_start) => {
+                let after = &rest[ref_start + "<ref>".len()..];
+                let end = after.find("</ref>").unwrap_or(after.len());
+                label = Some(after[..end].trim().to_string()).filter(|label| !label.is_empty());
+                rest = &after[end..];
+            }
+            (_, Some(box_start)) => {
+                let after = &rest[box_start + "<box>".len()..];
+                let end = after.find("</box>").unwrap_or(after.len());
+                let numbers = extract_numbers(&after[..end]);
+                for coords in numbers.chunks_exact(4) {
+                    boxes.push(BoundingBox {
+                        x1: coords[0],
+                        y1: coords[1],
+                        x2: coords[2],
+                        y2: coords[3],
+                        label: label.clone(),
+                        confidence: None,
+                    });
+                }
+                rest = &after[end..];
+            }
+            _ => break,
+        }
+    }
+    boxes
+}
+
+fn extract_numbers(text: &str) -> Vec<f32> {
+    text.split(|ch: char| !(ch.is_ascii_digit() || ch == '.' || ch == '-'))
+        .filter_map(|part| part.parse::<f32>().ok())
+        .collect()
+}
+
+fn parse_points(raw: &str) -> Result<Vec<Point2D>> {
+    let value: Value = serde_json::from_str(raw)
+        .map_err(|err| anyhow!("解析点坐标 JSON 失败: {err}, 原始响应: {raw}"))?;
+    let items = match value {
+        Value::Array(items) if items.len() == 2 && items.iter().all(Value::is_number) => {
+            vec![Value::Array(items)]
+        }
+        Value::Array(items) => items,
+        Value::Object(map) => match ["points", "point_2d", "point"]
+            .iter()
+            .find_map(|key| map.get(*key))
+        {
+            Some(Value::Array(items)) if items.iter().all(|item| !item.is_number()) => {
+                items.clone()
+            }
+            _ => vec![Value::Object(map)],
+        },
+        _ => Vec::new(),
+    };
+    let points: Vec<Point2D> = items.iter().filter_map(parse_point_from_value).collect();
+    if points.is_empty() {
+        return Err(anyhow!("未能从响应中解析出点坐标"));
+    }
+    Ok(points)
+}
+
+/// 解析单个点：`[x, y]`、`{"point_2d": [x, y]}`、`{"x": .., "y": ..}`，
+/// 只有边界框时取其中心点
+fn parse_point_from_value(value: &Value) -> Option<Point2D> {
+    let from_coords = |coords: &[Value]| {
+        Some((number_like(coords.first()?)?, number_like(coords.get(1)?)?))
+    };
     match value {
-        Value::Array(coords) if coords.len() >= 4 => {
-            let x1 = coords.get(0)?.as_f64()? as f32;
-            let y1 = coords.get(1)?.as_f64()? as f32;
-            let x2 = coords.get(2)?.as_f64()? as f32;
-            let y2 = coords.get(3)?.as_f64()? as f32;
-            Some(BoundingBox { x1, y1, x2, y2 })
+        Value::Array(coords) if coords.len() == 2 => {
+            let (x, y) = from_coords(coords)?;
+            Some(Point2D {
+                x,
+                y,
+                ..Default::default()
+            })
         }
         Value::Object(map) => {
-            for key in ["bbox_2d", "bbox", "box"] {
-                if let Some(Value::Array(coords)) = map.get(key) {
-                    if coords.len() >= 4 {
-                        let x1 = coords.get(0)?.as_f64()? as f32;
-                        let y1 = coords.get(1)?.as_f64()? as f32;
-                        let x2 = coords.get(2)?.as_f64()? as f32;
-                        let y2 = coords.get(3)?.as_f64()? as f32;
-                        return Some(BoundingBox { x1, y1, x2, y2 });
-                    }
-                }
-            }
-            let x1 = map.get("x1")?.as_f64()? as f32;
-            let y1 = map.get("y1")?.as_f64()? as f32;
-            let x2 = map.get("x2")?.as_f64()? as f32;
-            let y2 = map.get("y2")?.as_f64()? as f32;
-            Some(BoundingBox { x1, y1, x2, y2 })
+            let label = map
+                .get("label")
+                .and_then(Value::as_str)
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty());
+            let confidence = ["confidence", "score"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(Value::as_f64))
+                .map(|confidence| confidence as f32);
+            let point = ["point_2d", "point"]
+                .iter()
+                .find_map(|key| match map.get(*key) {
+                    Some(Value::Array(coords)) if coords.len() >= 2 => from_coords(coords),
+                    _ => None,
+                })
+                .or_else(|| {
+                    Some((number_like(map.get("x")?)?, number_like(map.get("y")?)?))
+                })
+                .or_else(|| {
+                    let bbox = parse_bbox_from_value(value)?;
+                    Some(((bbox.x1 + bbox.x2) / 2.0, (bbox.y1 + bbox.y2) / 2.0))
+                })?;
+            Some(Point2D {
+                x: point.0,
+                y: point.1,
+                label,
+                confidence,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 解析单个边界框：`[x1, y1, x2, y2]`、`{"bbox_2d": [...]}`、`{"x1": .., ...}` 等
+fn parse_bbox_from_value(value: &Value) -> Option<BoundingBox> {
+    match value {
+        Value::Array(coords) => bbox_from_coords(coords),
+        Value::Object(map) => {
+            let mut bbox = ["bbox_2d", "bbox", "box", "bounding_box"]
+                .iter()
+                .find_map(|key| match map.get(*key) {
+                    Some(Value::Array(coords)) => bbox_from_coords(coords),
+                    _ => None,
+                })
+                .or_else(|| {
+                    Some(BoundingBox {
+                        x1: number_like(map.get("x1")?)?,
+                        y1: number_like(map.get("y1")?)?,
+                        x2: number_like(map.get("x2")?)?,
+                        y2: number_like(map.get("y2")?)?,
+                        ..Default::default()
+                    })
+                })?;
+            bbox.label = object_label(map);
+            bbox.confidence = object_confidence(map);
+            Some(bbox)
         }
         _ => None,
     }
@@ -352,6 +1158,7 @@ fn parse_bbox_from_value(value: &Value) -> Option<BoundingBox> {
 pub async fn generate_image_with_zturbo(
     options: GenerateImageOptions,
     api_key: &str,
+    observer: &TaskObserver,
 ) -> Result<GenerateImageResult> {
     let client = Client::new();
 
@@ -361,10 +1168,10 @@ pub async fn generate_image_with_zturbo(
         "prompt": options.prompt,
     });
 
-    if let Some(ref neg) = options.negative_prompt {
-        if !neg.trim().is_empty() {
-            body["negative_prompt"] = json!(neg);
-        }
+    if let Some(ref neg) = options.negative_prompt
+        && !neg.trim().is_empty()
+    {
+        body["negative_prompt"] = json!(neg);
     }
     if let Some(ref size) = options.size {
         body["size"] = json!(size);
@@ -372,6 +1179,15 @@ pub async fn generate_image_with_zturbo(
     if let Some(steps) = options.steps {
         body["steps"] = json!(steps);
     }
+    if let Some(seed) = options.seed {
+        body["seed"] = json!(seed);
+    }
+    if let Some(guidance) = options.guidance {
+        body["guidance"] = json!(guidance);
+    }
+    if let Some(ref loras) = options.loras {
+        body["loras"] = json!(loras);
+    }
 
     // 调试日志：打印请求体
     eprintln!(
@@ -408,17 +1224,21 @@ pub async fn generate_image_with_zturbo(
         .task_id
         .ok_or_else(|| anyhow!("ModelScope 未返回 task_id"))?;
 
-    let (image_url, _) = poll_generation_task(&client, api_key, &task_id).await?;
-    Ok(GenerateImageResult { image_url, task_id })
+    let (image_urls, _) = poll_generation_task(&client, api_key, &task_id, observer).await?;
+    Ok(GenerateImageResult {
+        image_urls,
+        task_id,
+    })
 }
 
 pub async fn edit_image_with_qwen(
-    image_url: &str,
+    image_urls: &[String],
     prompt: &str,
     size: Option<&str>,
     steps: Option<u32>,
     api_key: &str,
-) -> Result<String> {
+    observer: &TaskObserver,
+) -> Result<GenerateImageResult> {
     let client = Client::new();
     let response = client
         .post(format!("{MODELSCOPE_API_ROOT}/v1/images/generations"))
@@ -426,7 +1246,7 @@ pub async fn edit_image_with_qwen(
         .header("X-ModelScope-Async-Mode", "true")
         .json(&json!({
             "model": QWEN_IMAGE_EDIT_MODEL,
-            "image_url": [image_url],
+            "image_url": image_urls,
             "prompt": prompt,
             "size": size,
             "steps": steps,
@@ -439,16 +1259,22 @@ pub async fn edit_image_with_qwen(
     let task_id = payload
         .task_id
         .ok_or_else(|| anyhow!("ModelScope 未返回 task_id"))?;
-    let (image_url, _) = poll_generation_task(&client, api_key, &task_id).await?;
-    Ok(image_url)
+    let (image_urls, _) = poll_generation_task(&client, api_key, &task_id, observer).await?;
+    Ok(GenerateImageResult {
+        image_urls,
+        task_id,
+    })
 }
 
 async fn poll_generation_task(
     client: &Client,
     api_key: &str,
     task_id: &str,
-) -> Result<(String, String)> {
-    let deadline = Instant::now() + Duration::from_millis(DEFAULT_TIMEOUT_MS);
+    observer: &TaskObserver,
+) -> Result<(Vec<String>, String)> {
+    let started_at = Instant::now();
+    let timeout = Duration::from_millis(DEFAULT_TIMEOUT_MS);
+    let deadline = started_at + timeout;
     let mut poll_count = 0u32;
 
     eprintln!(
@@ -458,12 +1284,17 @@ async fn poll_generation_task(
 
     while Instant::now() <= deadline {
         poll_count += 1;
-        let response = client
+        let request = client
             .get(format!("{MODELSCOPE_API_ROOT}/v1/tasks/{task_id}"))
             .bearer_auth(api_key)
             .header("X-ModelScope-Task-Type", "image_generation")
-            .send()
-            .await?;
+            .send();
+        let response = tokio::select! {
+            _ = observer.cancellation.cancelled() => {
+                return Err(anyhow!("ModelScope 图片生成已取消 (task_id={task_id})"));
+            }
+            response = request => response?,
+        };
 
         let response = assert_ok_response(response).await?;
         let response_text = response.text().await?;
@@ -481,18 +1312,25 @@ async fn poll_generation_task(
             .ok_or_else(|| anyhow!("ModelScope 未返回任务状态, 原始响应: {}", response_text))?;
 
         eprintln!("[DEBUG] poll_generation_task: task_status={}", status);
+        observer.report(TaskProgress {
+            task_id: task_id.to_string(),
+            task_status: status.clone(),
+            poll_count,
+            elapsed: started_at.elapsed(),
+            timeout,
+        });
 
         match status.as_str() {
             "SUCCEED" => {
-                let image_url = payload
+                let image_urls = payload
                     .output_images
-                    .and_then(|images| images.into_iter().next())
+                    .filter(|images| !images.is_empty())
                     .ok_or_else(|| anyhow!("ModelScope 未返回图片地址"))?;
                 eprintln!(
-                    "[DEBUG] poll_generation_task: success, image_url={}",
-                    image_url
+                    "[DEBUG] poll_generation_task: success, image_urls={:?}",
+                    image_urls
                 );
-                return Ok((image_url, task_id.to_string()));
+                return Ok((image_urls, task_id.to_string()));
             }
             "FAILED" => {
                 // 提取详细错误信息
@@ -514,7 +1352,12 @@ async fn poll_generation_task(
                     "[DEBUG] poll_generation_task: status={}, waiting...",
                     status
                 );
-                sleep(Duration::from_millis(DEFAULT_POLL_INTERVAL_MS)).await;
+                tokio::select! {
+                    _ = observer.cancellation.cancelled() => {
+                        return Err(anyhow!("ModelScope 图片生成已取消 (task_id={task_id})"));
+                    }
+                    _ = sleep(Duration::from_millis(DEFAULT_POLL_INTERVAL_MS)) => {}
+                }
             }
         }
     }
@@ -523,3 +1366,232 @@ async fn poll_generation_task(
         "ModelScope 图片生成超时 (task_id={task_id}, poll_count={poll_count})"
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type ExpectedBox = (f32, f32, f32, f32, Option<&'static str>);
+    type ExpectedPoint = (f32, f32, Option<&'static str>);
+
+    #[test]
+    fn parse_bounding_boxes_handles_real_model_outputs() {
+        let cases: &[(&str, &str, &[ExpectedBox])] = &[
+            (
+                "fenced json array",
+                "```json\n[\n\t{\"bbox_2d\": [123, 45, 678, 901], \"label\": \"cat\"}\n]\n```",
+                &[(123.0, 45.0, 678.0, 901.0, Some("cat"))],
+            ),
+            (
+                "fence without language tag",
+                "```\n[{\"bbox_2d\": [1, 2, 3, 4]}]\n```",
+                &[(1.0, 2.0, 3.0, 4.0, None)],
+            ),
+            (
+                "prose around fence",
+                "好的，以下是检测结果：\n```json\n[{\"bbox_2d\": [10, 20, 30, 40], \"label\": \"dog\"}]\n```\n希望对你有帮助。",
+                &[(10.0, 20.0, 30.0, 40.0, Some("dog"))],
+            ),
+            (
+                "json embedded in prose without fence",
+                "图中有两只猫：[{\"bbox_2d\": [1, 2, 3, 4], \"label\": \"猫\"}, {\"bbox_2d\": [5, 6, 7, 8], \"label\": \"猫\"}] 以上。",
+                &[(1.0, 2.0, 3.0, 4.0, Some("猫")), (5.0, 6.0, 7.0, 8.0, Some("猫"))],
+            ),
+            (
+                "braces inside label string",
+                "结果：{\"bbox_2d\": [1, 2, 3, 4], \"label\": \"a {b}]\"}",
+                &[(1.0, 2.0, 3.0, 4.0, Some("a {b}]"))],
+            ),
+            (
+                "qwen ref and box tags",
+                "<ref>猫</ref><box>(100,200),(300,400)</box>",
+                &[(100.0, 200.0, 300.0, 400.0, Some("猫"))],
+            ),
+            (
+                "qwen special tokens with several boxes",
+                "<|object_ref_start|>person<|object_ref_end|><|box_start|>(12,34),(56,78)<|box_end|><|box_start|>(1,2),(3,4)<|box_end|>",
+                &[(12.0, 34.0, 56.0, 78.0, Some("person")), (1.0, 2.0, 3.0, 4.0, Some("person"))],
+            ),
+            (
+                "box tags in prose without ref",
+                "The car is at <box>(5, 6), (7, 8)</box>.",
+                &[(5.0, 6.0, 7.0, 8.0, None)],
+            ),
+            (
+                "nested coordinate arrays under one label",
+                "{\"bbox_2d\": [[1, 2, 3, 4], [5, 6, 7, 8]], \"label\": \"car\"}",
+                &[(1.0, 2.0, 3.0, 4.0, Some("car")), (5.0, 6.0, 7.0, 8.0, Some("car"))],
+            ),
+            (
+                "bare nested arrays",
+                "[[1, 2, 3, 4], [5, 6, 7, 8]]",
+                &[(1.0, 2.0, 3.0, 4.0, None), (5.0, 6.0, 7.0, 8.0, None)],
+            ),
+            (
+                "two-point box",
+                "[{\"bbox_2d\": [[1, 2], [3, 4]], \"label\": \"pen\"}]",
+                &[(1.0, 2.0, 3.0, 4.0, Some("pen"))],
+            ),
+            (
+                "string numbers",
+                "[{\"bbox_2d\": [\"10\", \"20\", \"30.5\", \"40\"], \"label\": \"sign\"}]",
+                &[(10.0, 20.0, 30.5, 40.0, Some("sign"))],
+            ),
+            (
+                "single flat array",
+                "[1, 2, 3, 4]",
+                &[(1.0, 2.0, 3.0, 4.0, None)],
+            ),
+            (
+                "x1 y1 x2 y2 object",
+                "{\"x1\": 1, \"y1\": 2, \"x2\": 3, \"y2\": 4}",
+                &[(1.0, 2.0, 3.0, 4.0, None)],
+            ),
+            (
+                "wrapper with name field",
+                "{\"objects\": [{\"name\": \"cup\", \"bbox\": [1, 2, 3, 4]}]}",
+                &[(1.0, 2.0, 3.0, 4.0, Some("cup"))],
+            ),
+        ];
+
+        for (name, raw, expected) in cases {
+            let boxes = parse_bounding_boxes(raw)
+                .unwrap_or_else(|err| panic!("{name}: parse failed: {err}"));
+            assert_eq!(boxes.len(), expected.len(), "{name}: box count");
+            for (index, (bbox, expected)) in boxes.iter().zip(expected.iter()).enumerate() {
+                assert_eq!(
+                    (bbox.x1, bbox.y1, bbox.x2, bbox.y2),
+                    (expected.0, expected.1, expected.2, expected.3),
+                    "{name}: box {index} coordinates"
+                );
+                assert_eq!(bbox.label.as_deref(), expected.4, "{name}: box {index} label");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_bounding_boxes_keeps_confidence() {
+        let boxes =
+            parse_bounding_boxes("[{\"bbox_2d\": [1, 2, 3, 4], \"label\": \"a\", \"score\": \"0.8\"}]")
+                .unwrap();
+        assert_eq!(boxes[0].confidence, Some(0.8));
+    }
+
+    #[test]
+    fn parse_points_handles_model_outputs() {
+        let cases: &[(&str, &str, &[ExpectedPoint])] = &[
+            (
+                "point_2d objects",
+                r#"[{"point_2d": [100, 200], "label": "按钮"}, {"point_2d": ["300", 400]}]"#,
+                &[(100.0, 200.0, Some("按钮")), (300.0, 400.0, None)],
+            ),
+            ("single bare point", "[12, 34]", &[(12.0, 34.0, None)]),
+            (
+                "array of bare points",
+                "[[1, 2], [3, 4]]",
+                &[(1.0, 2.0, None), (3.0, 4.0, None)],
+            ),
+            (
+                "points wrapper",
+                r#"{"points": [{"x": 5, "y": 6, "label": "a"}]}"#,
+                &[(5.0, 6.0, Some("a"))],
+            ),
+            (
+                "single object with point_2d",
+                r#"{"point_2d": [7, 8], "label": "b"}"#,
+                &[(7.0, 8.0, Some("b"))],
+            ),
+            (
+                "box falls back to center",
+                r#"[{"bbox_2d": [0, 0, 100, 50], "label": "c"}]"#,
+                &[(50.0, 25.0, Some("c"))],
+            ),
+        ];
+
+        for (name, raw, expected) in cases {
+            let points = parse_points(raw).unwrap_or_else(|err| panic!("{name}: parse failed: {err}"));
+            assert_eq!(points.len(), expected.len(), "{name}: point count");
+            for (index, (point, expected)) in points.iter().zip(expected.iter()).enumerate() {
+                assert_eq!((point.x, point.y), (expected.0, expected.1), "{name}: point {index}");
+                assert_eq!(point.label.as_deref(), expected.2, "{name}: point {index} label");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_points_rejects_outputs_without_points() {
+        for raw in ["[]", r#"{"label": "a"}"#, "not json", "[1, 2, 3]"] {
+            assert!(parse_points(raw).is_err(), "expected error for {raw:?}");
+        }
+        let points = parse_points(r#"[{"point_2d": [1, 2], "score": 0.7}]"#).unwrap();
+        assert_eq!(points[0].confidence, Some(0.7));
+    }
+
+    #[test]
+    fn parse_bounding_boxes_rejects_outputs_without_boxes() {
+        for raw in [
+            "图中没有找到目标。",
+            "[]",
+            "{\"label\": \"cat\"}",
+            "```json\n[]\n```",
+            "{\"size\": [[1, 2, 3, 4]]}",
+            "{\"image\": {\"size\": [1, 2, 3, 4]}}",
+            "[{\"bbox_2d\": [10, 20, 30, 40, 50], \"label\": \"cat\"}]",
+            "[{\"bbox_2d\": [10, 20, 30, 20, 30, 40, 10, 40], \"label\": \"cat\"}]",
+        ] {
+            let err = parse_bounding_boxes(raw).unwrap_err();
+            assert!(err.downcast_ref::<NoBoundingBoxes>().is_some(), "expected no boxes for {raw:?}");
+            assert!(boxes_or_empty(parse_bounding_boxes(raw)).unwrap().is_empty());
+        }
+        assert!(boxes_or_empty(Err(anyhow!("HTTP 401"))).is_err());
+    }
+
+    #[test]
+    fn parse_ocr_layout_reads_blocks_from_arrays_and_wrappers() {
+        let raw = "```json\n[{\"text\": \"标题\", \"bbox_2d\": [10, 20, 300, 60]}, {\"content\": \"正文\", \"bbox\": [10, 80, 900, 200], \"confidence\": 0.8}]\n```";
+        let blocks = parse_ocr_layout(raw).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].text, "标题");
+        assert_eq!(
+            (blocks[0].bbox.x1, blocks[0].bbox.y1, blocks[0].bbox.x2, blocks[0].bbox.y2),
+            (10.0, 20.0, 300.0, 60.0)
+        );
+        assert_eq!(blocks[1].text, "正文");
+        assert_eq!(blocks[1].confidence, Some(0.8));
+
+        let wrapped = r#"{"lines": [{"text": "a", "bbox_2d": [1, 2, 3, 4]}]}"#;
+        assert_eq!(parse_ocr_layout(wrapped).unwrap()[0].text, "a");
+    }
+
+    #[test]
+    fn parse_ocr_layout_handles_empty_and_invalid_outputs() {
+        assert!(parse_ocr_layout("[]").unwrap().is_empty());
+        // 有内容但没有任何带坐标的文本块
+        assert!(parse_ocr_layout(r#"[{"text": "a"}]"#).is_err());
+        assert!(parse_ocr_layout("not json").is_err());
+    }
+
+    #[test]
+    fn parse_ocr_tables_builds_rows_and_csv() {
+        let raw = r#"{"tables": [{"title": " 价格表 ", "rows": [["名称", "价格"], ["苹果, 红", 3.5], ["说\"明\"", null]]}]}"#;
+        let tables = parse_ocr_tables(raw).unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].title.as_deref(), Some("价格表"));
+        assert_eq!(tables[0].rows[1], vec!["苹果, 红", "3.5"]);
+        assert_eq!(tables[0].rows[2], vec!["说\"明\"", ""]);
+        assert_eq!(
+            tables[0].csv,
+            "名称,价格\n\"苹果, 红\",3.5\n\"说\"\"明\"\"\","
+        );
+    }
+
+    #[test]
+    fn parse_ocr_tables_accepts_bare_row_arrays() {
+        let tables = parse_ocr_tables("[[[\"a\", \"b\"], [\"1\", \"2\"]]]").unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].title, None);
+        assert_eq!(tables[0].csv, "a,b\n1,2");
+        assert!(parse_ocr_tables("[]").unwrap().is_empty());
+    }
+}
+
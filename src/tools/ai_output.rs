@@ -0,0 +1,167 @@
+use chrono::Utc;
+use rmcp::ErrorData as McpError;
+
+use crate::{
+    cache::{
+        EditedImageCacheMetadata, GeneratedImageCacheMetadata, LocalFileStorage, compute_hash,
+        get_extension_from_mime_type,
+    },
+    image_processing,
+    metadata_strip::{self, MetadataPolicy},
+    tools::{DownloadedImage, download_image},
+};
+
+struct StoredOutput {
+    cached_image_key: String,
+    cached_image_url: String,
+    mime_type: String,
+}
+
+/// 下载生图结果并存入 `generated/` 缓存，返回稳定的 /cache 地址
+pub async fn persist_generated_image(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+    upstream_image_url: &str,
+) -> Result<GeneratedImageCacheMetadata, McpError> {
+    let prefix = format!("generated/{}", compute_hash(cache_key_input));
+    let stored = download_and_store(storage, &prefix, upstream_image_url).await?;
+    let metadata = GeneratedImageCacheMetadata {
+        cache_key_input: cache_key_input.to_string(),
+        cached_image_key: stored.cached_image_key,
+        cached_image_url: stored.cached_image_url,
+        upstream_image_url: upstream_image_url.to_string(),
+        mime_type: stored.mime_type,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    save_metadata(storage, &prefix, &metadata).await?;
+    Ok(metadata)
+}
+
+/// 下载编辑结果并存入 `edited/` 缓存，返回稳定的 /cache 地址
+pub async fn persist_edited_image(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+    upstream_image_url: &str,
+) -> Result<EditedImageCacheMetadata, McpError> {
+    let prefix = format!("edited/{}", compute_hash(cache_key_input));
+    let stored = download_and_store(storage, &prefix, upstream_image_url).await?;
+    let metadata = EditedImageCacheMetadata {
+        cache_key_input: cache_key_input.to_string(),
+        cached_image_key: stored.cached_image_key,
+        cached_image_url: stored.cached_image_url,
+        upstream_image_url: upstream_image_url.to_string(),
+        mime_type: stored.mime_type,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    save_metadata(storage, &prefix, &metadata).await?;
+    Ok(metadata)
+}
+
+/// 将本地合成后的编辑结果存入 `edited/` 缓存，元数据保留模型原始输出地址
+pub async fn persist_edited_image_bytes(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+    upstream_image_url: &str,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<EditedImageCacheMetadata, McpError> {
+    let prefix = format!("edited/{}", compute_hash(cache_key_input));
+    let stored = store_bytes(storage, &prefix, bytes, mime_type).await?;
+    let metadata = EditedImageCacheMetadata {
+        cache_key_input: cache_key_input.to_string(),
+        cached_image_key: stored.cached_image_key,
+        cached_image_url: stored.cached_image_url,
+        upstream_image_url: upstream_image_url.to_string(),
+        mime_type: stored.mime_type,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    save_metadata(storage, &prefix, &metadata).await?;
+    Ok(metadata)
+}
+
+/// 若 URL 指向本服务的 /cache 地址，直接读取缓存文件；模型服务无法访问本地地址，
+/// 魔搭返回的原始地址也会过期，需由调用方转为 data URL 发送。URL 不属于缓存或文件不存在时返回 None
+pub async fn read_cached_image(storage: &LocalFileStorage, url: &str) -> Option<DownloadedImage> {
+    let key = storage.key_from_public_url(url)?;
+    let bytes = storage.get(&key).await.ok().flatten()?;
+    let mime_type = image_processing::detect_mime_type(&bytes)?.to_string();
+    Some(DownloadedImage { bytes, mime_type })
+}
+
+/// 优先读取本服务缓存，否则下载原图；本服务 `/cache` 地址不经过网络读取
+pub async fn read_or_download_image(
+    storage: &LocalFileStorage,
+    url: &str,
+) -> Result<DownloadedImage, McpError> {
+    match read_cached_image(storage, url).await {
+        Some(cached) => Ok(cached),
+        None => download_image(url).await,
+    }
+}
+
+async fn download_and_store(
+    storage: &LocalFileStorage,
+    prefix: &str,
+    upstream_image_url: &str,
+) -> Result<StoredOutput, McpError> {
+    let downloaded = download_image(upstream_image_url).await?;
+    store_bytes(storage, prefix, &downloaded.bytes, &downloaded.mime_type).await
+}
+
+async fn store_bytes(
+    storage: &LocalFileStorage,
+    prefix: &str,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<StoredOutput, McpError> {
+    // 去除失败时保存原始输出：结果来自模型服务，不应因此让整个生成失败
+    let stripped = match MetadataPolicy::for_outputs() {
+        MetadataPolicy::Strip => match metadata_strip::strip_metadata(bytes, mime_type) {
+            Ok(stripped) => Some(stripped),
+            Err(err) => {
+                eprintln!("[WARN] strip output metadata failed: {err}, prefix={prefix}");
+                None
+            }
+        },
+        MetadataPolicy::Keep => None,
+    };
+    // 摆正方向后可能转换了格式，扩展名与 MIME 类型以去除后的图片为准
+    let (bytes, mime_type) = match &stripped {
+        Some(stripped) => (stripped.bytes.as_slice(), stripped.mime_type.as_str()),
+        None => (bytes, mime_type),
+    };
+    let ext = get_extension_from_mime_type(mime_type);
+    let cached_image_key = LocalFileStorage::get_result_key(prefix, ext);
+    storage.put(&cached_image_key, bytes).await.map_err(|err| {
+        McpError::internal_error(
+            "cache ai image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let cached_image_url = storage.get_public_url(&cached_image_key);
+    Ok(StoredOutput {
+        cached_image_key,
+        cached_image_url,
+        mime_type: mime_type.to_string(),
+    })
+}
+
+async fn save_metadata<T: serde::Serialize>(
+    storage: &LocalFileStorage,
+    prefix: &str,
+    metadata: &T,
+) -> Result<(), McpError> {
+    let meta_key = LocalFileStorage::get_meta_key(prefix);
+    let meta_json = serde_json::to_vec(metadata).map_err(|err| {
+        McpError::internal_error(
+            "serialize cache metadata failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    storage.put(&meta_key, &meta_json).await.map_err(|err| {
+        McpError::internal_error(
+            "save cache metadata failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })
+}
@@ -0,0 +1,82 @@
+use rmcp::ErrorData as McpError;
+
+use crate::image_processing;
+
+/// 未指定颜色时按序循环使用的标注色
+const DEFAULT_BOX_COLORS: [[u8; 4]; 6] = [
+    [255, 59, 48, 255],
+    [52, 199, 89, 255],
+    [0, 122, 255, 255],
+    [255, 204, 0, 255],
+    [175, 82, 222, 255],
+    [255, 149, 0, 255],
+];
+
+/// 解析 `#RRGGBB` / `RRGGBB` 或常用颜色名
+pub fn parse_color(value: &str) -> Result<[u8; 4], McpError> {
+    let normalized = value.trim().to_ascii_lowercase();
+    let named = match normalized.as_str() {
+        "red" => Some([255, 0, 0, 255]),
+        "green" => Some([0, 200, 0, 255]),
+        "blue" => Some([0, 90, 255, 255]),
+        "yellow" => Some([255, 220, 0, 255]),
+        "orange" => Some([255, 140, 0, 255]),
+        "purple" => Some([160, 32, 240, 255]),
+        "white" => Some([255, 255, 255, 255]),
+        "black" => Some([0, 0, 0, 255]),
+        _ => None,
+    };
+    if let Some(color) = named {
+        return Ok(color);
+    }
+    let hex = normalized.trim_start_matches('#');
+    let channel = |range: std::ops::Range<usize>| {
+        hex.get(range)
+            .and_then(|part| u8::from_str_radix(part, 16).ok())
+    };
+    match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b, 255]),
+        _ => Err(McpError::invalid_params(
+            format!("无法识别的颜色：{value}，请使用 #RRGGBB 或 red、green、blue 等颜色名"),
+            None,
+        )),
+    }
+}
+
+/// 解析调用方给出的颜色列表，未提供时使用默认配色
+pub fn parse_colors(colors: Option<&[String]>) -> Result<Vec<[u8; 4]>, McpError> {
+    match colors {
+        Some(colors) if !colors.is_empty() => colors.iter().map(|color| parse_color(color)).collect(),
+        _ => Ok(DEFAULT_BOX_COLORS.to_vec()),
+    }
+}
+
+/// 在图片上绘制像素矩形框，并在框左上角标注从 1 开始的序号（空区域跳过但保留序号）
+pub fn draw_boxes(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    rects: &[Option<(u32, u32, u32, u32)>],
+    colors: &[[u8; 4]],
+    line_width: Option<u32>,
+) {
+    let short_edge = width.min(height);
+    let thickness = line_width.unwrap_or((short_edge / 300).max(2));
+    let label_scale = (short_edge / 250).max(2);
+    for (index, rect) in rects.iter().enumerate() {
+        let Some(rect) = rect else {
+            continue;
+        };
+        let color = colors[index % colors.len()];
+        image_processing::draw_rect_outline(pixels, width, height, *rect, thickness, color);
+        image_processing::draw_number_label(
+            pixels,
+            width,
+            height,
+            (rect.0, rect.1),
+            index + 1,
+            label_scale,
+            color,
+        );
+    }
+}
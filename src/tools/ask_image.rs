@@ -0,0 +1,211 @@
+use rmcp::{
+    ErrorData as McpError,
+    handler::server::wrapper::Parameters,
+    model::{CallToolResult, Content},
+    schemars::JsonSchema,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    cache::LocalFileStorage,
+    modelscope,
+    tools::{image_data_url, model_image_max_edge, read_or_download_image, validate_http_url},
+};
+
+const MAX_QUESTION_IMAGES: usize = 4;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AskImageRequest {
+    #[schemars(description = "图像URL列表（最多 4 张，按顺序称为图1、图2…）")]
+    pub image_urls: Vec<String>,
+    #[schemars(description = "针对图片的问题，例如“这张发票的总金额是多少？”")]
+    pub question: String,
+    #[schemars(
+        description = "可选：回答需要符合的 JSON Schema（如 {\"type\":\"object\",\"properties\":{\"total\":{\"type\":\"number\"}},\"required\":[\"total\"]}），提供时返回结构化 JSON；回答不是 JSON 对象时放在 answer 字段中"
+    )]
+    pub json_schema: Option<Value>,
+}
+
+pub async fn ask_image(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<AskImageRequest>,
+) -> Result<CallToolResult, McpError> {
+    if request.image_urls.is_empty() {
+        return Err(McpError::invalid_params("image_urls 不能为空", None));
+    }
+    if request.image_urls.len() > MAX_QUESTION_IMAGES {
+        return Err(McpError::invalid_params(
+            format!("最多支持 {MAX_QUESTION_IMAGES} 张图片"),
+            None,
+        ));
+    }
+    if request.question.trim().is_empty() {
+        return Err(McpError::invalid_params("question 不能为空", None));
+    }
+    if let Some(schema) = &request.json_schema
+        && !schema.is_object()
+    {
+        return Err(McpError::invalid_params("json_schema 必须是 JSON 对象", None));
+    }
+    let api_key = std::env::var("MODELSCOPE_API_KEY")
+        .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+    if api_key.trim().is_empty() {
+        return Err(McpError::internal_error(
+            "missing MODELSCOPE_API_KEY",
+            None,
+        ));
+    }
+
+    let max_edge = model_image_max_edge();
+    let mut model_image_urls = Vec::with_capacity(request.image_urls.len());
+    for raw_url in &request.image_urls {
+        let validated_url = validate_http_url(raw_url)?;
+        let image = read_or_download_image(storage, validated_url.as_str()).await?;
+        model_image_urls.push(image_data_url(&image.bytes, &image.mime_type, max_edge)?);
+    }
+
+    let answer = modelscope::ask_images_with_qwen(
+        &model_image_urls,
+        &request.question,
+        request.json_schema.as_ref(),
+        &api_key,
+    )
+    .await
+    .map_err(|err| {
+        McpError::internal_error(
+            "ask image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+
+    let Some(schema) = &request.json_schema else {
+        return Ok(CallToolResult::success(vec![Content::text(answer)]));
+    };
+    let parsed = modelscope::parse_json_answer(&answer).map_err(|err| {
+        McpError::internal_error(
+            "parse structured answer failed",
+            Some(serde_json::json!({ "error": err.to_string(), "raw": answer })),
+        )
+    })?;
+    let mut violations = Vec::new();
+    check_schema(&parsed, schema, "$", &mut violations);
+    if !violations.is_empty() {
+        return Err(McpError::internal_error(
+            "answer does not match json_schema",
+            Some(serde_json::json!({ "violations": violations, "raw": answer })),
+        ));
+    }
+    // structuredContent 必须是 JSON 对象，数组、字符串等回答放在 answer 字段中返回
+    let structured = match parsed {
+        Value::Object(_) => parsed,
+        other => serde_json::json!({ "answer": other }),
+    };
+    Ok(CallToolResult::structured(structured))
+}
+
+/// 按 JSON Schema 的常用子集（type、required、properties、items、enum）校验模型输出
+fn check_schema(value: &Value, schema: &Value, path: &str, violations: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let matches = match expected {
+            Value::String(kind) => type_matches(value, kind),
+            Value::Array(kinds) => kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|kind| type_matches(value, kind)),
+            _ => true,
+        };
+        if !matches {
+            violations.push(format!("{path}: 类型应为 {expected}"));
+            return;
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        violations.push(format!("{path}: 取值不在 enum 范围内"));
+    }
+    if let Value::Object(object) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(field) {
+                    violations.push(format!("{path}.{field}: 缺少必填字段"));
+                }
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (field, field_schema) in properties {
+                if let Some(field_value) = object.get(field) {
+                    check_schema(field_value, field_schema, &format!("{path}.{field}"), violations);
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check_schema(item, item_schema, &format!("{path}[{index}]"), violations);
+        }
+    }
+}
+
+fn type_matches(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        // 模型常把整数写成 3.0，小数部分为 0 的浮点数也视为整数
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|number| number.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn violations(value: &Value, schema: &Value) -> Vec<String> {
+        let mut violations = Vec::new();
+        check_schema(value, schema, "$", &mut violations);
+        violations
+    }
+
+    #[test]
+    fn type_matches_accepts_integral_floats_as_integers() {
+        assert!(type_matches(&json!(3), "integer"));
+        assert!(type_matches(&json!(3.0), "integer"));
+        assert!(!type_matches(&json!(3.5), "integer"));
+        assert!(!type_matches(&json!("3"), "integer"));
+        assert!(type_matches(&json!(3.5), "number"));
+    }
+
+    #[test]
+    fn check_schema_reports_nested_violations() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "total": {"type": "number"},
+                "items": {"type": "array", "items": {"type": "string"}},
+                "currency": {"enum": ["CNY", "USD"]}
+            },
+            "required": ["total"]
+        });
+        assert!(violations(&json!({"total": 12.5, "items": ["a"], "currency": "CNY"}), &schema).is_empty());
+        assert_eq!(
+            violations(&json!({"items": ["a", 1], "currency": "EUR"}), &schema),
+            vec![
+                "$.total: 缺少必填字段".to_string(),
+                "$.currency: 取值不在 enum 范围内".to_string(),
+                "$.items[1]: 类型应为 \"string\"".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::modelscope::BoundingBox;
+
+/// 两个边界框的交并比，坐标系无关（对 x、y 分别缩放不影响结果）
+pub fn box_iou(a: &BoundingBox, b: &BoundingBox) -> f32 {
+    let (ax1, ax2) = (a.x1.min(a.x2), a.x1.max(a.x2));
+    let (ay1, ay2) = (a.y1.min(a.y2), a.y1.max(a.y2));
+    let (bx1, bx2) = (b.x1.min(b.x2), b.x1.max(b.x2));
+    let (by1, by2) = (b.y1.min(b.y2), b.y1.max(b.y2));
+    let intersection_width = (ax2.min(bx2) - ax1.max(bx1)).max(0.0);
+    let intersection_height = (ay2.min(by2) - ay1.max(by1)).max(0.0);
+    let intersection = intersection_width * intersection_height;
+    let union = (ax2 - ax1) * (ay2 - ay1) + (bx2 - bx1) * (by2 - by1) - intersection;
+    if union > 0.0 { intersection / union } else { 0.0 }
+}
+
+/// 非极大值抑制：按置信度从高到低保留，与已保留框 IoU 超过阈值的视为重复。
+/// 模型未给出置信度时按原始顺序处理。
+pub fn non_max_suppression(boxes: Vec<BoundingBox>, iou_threshold: f32) -> Vec<BoundingBox> {
+    let mut ordered = boxes;
+    ordered.sort_by(|a, b| {
+        b.confidence
+            .unwrap_or(1.0)
+            .total_cmp(&a.confidence.unwrap_or(1.0))
+    });
+    let mut kept: Vec<BoundingBox> = Vec::with_capacity(ordered.len());
+    for bbox in ordered {
+        if kept
+            .iter()
+            .all(|existing| box_iou(existing, &bbox) <= iou_threshold)
+        {
+            kept.push(bbox);
+        }
+    }
+    kept
+}
+
+/// 过滤宽或高小于 `min_size`（0-999 坐标单位）的边界框
+pub fn filter_min_size(boxes: Vec<BoundingBox>, min_size: f32) -> Vec<BoundingBox> {
+    boxes
+        .into_iter()
+        .filter(|bbox| {
+            (bbox.x2 - bbox.x1).abs() >= min_size && (bbox.y2 - bbox.y1).abs() >= min_size
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(x1: f32, y1: f32, x2: f32, y2: f32, confidence: Option<f32>) -> BoundingBox {
+        BoundingBox {
+            x1,
+            y1,
+            x2,
+            y2,
+            label: None,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn box_iou_handles_overlap_disjoint_and_reversed_corners() {
+        let a = bbox(0.0, 0.0, 10.0, 10.0, None);
+        assert_eq!(box_iou(&a, &a), 1.0);
+        // 交集 50，并集 150
+        let b = bbox(5.0, 0.0, 15.0, 10.0, None);
+        assert!((box_iou(&a, &b) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(box_iou(&a, &bbox(20.0, 20.0, 30.0, 30.0, None)), 0.0);
+        let reversed = bbox(15.0, 10.0, 5.0, 0.0, None);
+        assert!((box_iou(&a, &reversed) - 1.0 / 3.0).abs() < 1e-6);
+        let empty = bbox(1.0, 1.0, 1.0, 1.0, None);
+        assert_eq!(box_iou(&empty, &empty), 0.0);
+    }
+
+    #[test]
+    fn non_max_suppression_keeps_highest_confidence_duplicates() {
+        let boxes = vec![
+            bbox(0.0, 0.0, 100.0, 100.0, Some(0.6)),
+            bbox(2.0, 2.0, 102.0, 102.0, Some(0.9)),
+            bbox(500.0, 500.0, 600.0, 600.0, Some(0.5)),
+        ];
+        let kept = non_max_suppression(boxes, 0.5);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].confidence, Some(0.9));
+        assert_eq!(kept[1].confidence, Some(0.5));
+    }
+
+    #[test]
+    fn non_max_suppression_keeps_order_without_confidence() {
+        let boxes = vec![
+            bbox(0.0, 0.0, 100.0, 100.0, None),
+            bbox(0.0, 0.0, 100.0, 90.0, None),
+            bbox(0.0, 0.0, 100.0, 40.0, None),
+        ];
+        let kept = non_max_suppression(boxes, 0.5);
+        // 第二个框与第一个 IoU 为 0.9 被抑制，第三个 IoU 为 0.4 保留
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].y2, 100.0);
+        assert_eq!(kept[1].y2, 40.0);
+    }
+
+    #[test]
+    fn filter_min_size_drops_small_boxes_on_either_axis() {
+        let boxes = vec![
+            bbox(0.0, 0.0, 20.0, 20.0, None),
+            bbox(0.0, 0.0, 5.0, 20.0, None),
+            bbox(0.0, 20.0, 20.0, 15.0, None),
+        ];
+        let kept = filter_min_size(boxes, 10.0);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].x2, 20.0);
+    }
+}
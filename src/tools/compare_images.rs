@@ -0,0 +1,208 @@
+use base64::Engine;
+use rmcp::{
+    ErrorData as McpError,
+    handler::server::wrapper::Parameters,
+    model::{CallToolResult, Content},
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    image_processing, modelscope,
+    tools::{
+        DownloadedImage, image_data_url, model_image_max_edge, persist_processed_png,
+        read_or_download_image, validate_http_url,
+    },
+};
+
+/// 任一通道差值超过该阈值才计为变化像素，过滤 JPEG 压缩噪点
+const CHANGE_THRESHOLD: u8 = 16;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CompareImagesRequest {
+    #[schemars(description = "修改前的图像URL")]
+    pub before_url: String,
+    #[schemars(description = "修改后的图像URL")]
+    pub after_url: String,
+    #[schemars(description = "是否调用视觉模型描述语义差异，默认 false")]
+    pub semantic: Option<bool>,
+    #[schemars(description = "语义对比时需要关注的内容")]
+    pub focus: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImageSummary {
+    url: String,
+    mime_type: String,
+    width: u32,
+    height: u32,
+    size: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CompareResult {
+    before: ImageSummary,
+    after: ImageSummary,
+    same_dimensions: bool,
+    same_format: bool,
+    /// 尺寸不一致时，修改后的图片会缩放到修改前的尺寸再逐像素比较
+    resized_for_comparison: bool,
+    /// 两图像素完全一致时为 null
+    psnr: Option<f64>,
+    ssim: f64,
+    changed_pixel_ratio: f64,
+    /// 差异图缓存失败时为 null，差异图以内联图片返回
+    diff_image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    semantic_diff: Option<String>,
+    /// 语义对比失败的原因，不影响像素级结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    semantic_diff_error: Option<String>,
+    text: String,
+}
+
+pub async fn compare_images(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<CompareImagesRequest>,
+) -> Result<CallToolResult, McpError> {
+    let before_url = validate_http_url(&request.before_url)?.to_string();
+    let after_url = validate_http_url(&request.after_url)?.to_string();
+    let before = read_or_download_image(storage, &before_url).await?;
+    let after = read_or_download_image(storage, &after_url).await?;
+
+    let (before_pixels, width, height) = decode(&before.bytes, &before.mime_type)?;
+    let (after_pixels, after_width, after_height) = decode(&after.bytes, &after.mime_type)?;
+    let same_dimensions = width == after_width && height == after_height;
+    let after_pixels = if same_dimensions {
+        after_pixels
+    } else {
+        image_processing::resize_pixels(&after_pixels, after_width, after_height, width, height)
+            .map_err(|err| {
+                McpError::internal_error(
+                    "resize image failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?
+    };
+
+    let psnr = image_processing::psnr(&before_pixels, &after_pixels);
+    let ssim = image_processing::ssim(&before_pixels, &after_pixels, width, height);
+    let changed_pixel_ratio =
+        image_processing::changed_pixel_ratio(&before_pixels, &after_pixels, CHANGE_THRESHOLD);
+
+    // 同一 URL 的内容可能变化，缓存 key 同时包含两张图片的内容哈希
+    let cache_key_input = format!(
+        "compare:{}:{}:{}:{}",
+        before_url,
+        after_url,
+        compute_bytes_hash(&before.bytes),
+        compute_bytes_hash(&after.bytes)
+    );
+    let heatmap = image_processing::diff_heatmap(&before_pixels, &after_pixels);
+    let mut contents = Vec::new();
+    let diff_image_url =
+        match persist_processed_png(storage, &cache_key_input, &heatmap, width, height).await {
+            Ok(url) => Some(url),
+            Err(err) => {
+                // 缓存失败时直接内联返回差异图
+                eprintln!("[WARN] compare_images: cache diff image failed: {:?}", err);
+                let diff_bytes =
+                    image_processing::encode_png(&heatmap, width, height).map_err(|err| {
+                        McpError::internal_error(
+                            "encode image failed",
+                            Some(serde_json::Value::String(err.to_string())),
+                        )
+                    })?;
+                let base64_image = base64::engine::general_purpose::STANDARD.encode(&diff_bytes);
+                contents.push(Content::image(base64_image, "image/png"));
+                None
+            }
+        };
+
+    let (semantic_diff, semantic_diff_error) = if request.semantic.unwrap_or(false) {
+        match semantic_diff(&before, &after, request.focus.as_deref()).await {
+            Ok(diff) => (Some(diff), None),
+            Err(err) => (None, Some(err)),
+        }
+    } else {
+        (None, None)
+    };
+
+    let text = format!(
+        "尺寸：{}x{} → {}x{}；格式：{} → {}；PSNR：{}；SSIM：{:.4}；变化像素占比：{:.2}%。",
+        width,
+        height,
+        after_width,
+        after_height,
+        before.mime_type,
+        after.mime_type,
+        psnr.map(|value| format!("{value:.2} dB"))
+            .unwrap_or_else(|| "∞（像素完全一致）".to_string()),
+        ssim,
+        changed_pixel_ratio * 100.0
+    );
+    let result = CompareResult {
+        same_format: before.mime_type == after.mime_type,
+        before: ImageSummary {
+            url: before_url,
+            mime_type: before.mime_type,
+            width,
+            height,
+            size: before.bytes.len(),
+        },
+        after: ImageSummary {
+            url: after_url,
+            mime_type: after.mime_type,
+            width: after_width,
+            height: after_height,
+            size: after.bytes.len(),
+        },
+        same_dimensions,
+        resized_for_comparison: !same_dimensions,
+        psnr,
+        ssim,
+        changed_pixel_ratio,
+        diff_image_url,
+        semantic_diff,
+        semantic_diff_error,
+        text,
+    };
+    let json = serde_json::to_string(&result).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    contents.push(Content::text(json));
+    Ok(CallToolResult::success(contents))
+}
+
+fn decode(bytes: &[u8], mime_type: &str) -> Result<(Vec<u8>, u32, u32), McpError> {
+    image_processing::decode_image(bytes, mime_type).map_err(|err| {
+        McpError::internal_error(
+            "decode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })
+}
+
+/// 语义对比失败不影响像素级结果，错误信息单独返回
+async fn semantic_diff(
+    before: &DownloadedImage,
+    after: &DownloadedImage,
+    focus: Option<&str>,
+) -> Result<String, String> {
+    let api_key = match std::env::var("MODELSCOPE_API_KEY") {
+        Ok(api_key) if !api_key.trim().is_empty() => api_key,
+        _ => return Err("missing MODELSCOPE_API_KEY".to_string()),
+    };
+    let max_edge = model_image_max_edge();
+    let before_url = image_data_url(&before.bytes, &before.mime_type, max_edge)
+        .map_err(|err| err.message.to_string())?;
+    let after_url = image_data_url(&after.bytes, &after.mime_type, max_edge)
+        .map_err(|err| err.message.to_string())?;
+    modelscope::describe_image_differences_with_qwen(&before_url, &after_url, &api_key, focus)
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,160 @@
+use rmcp::{
+    ErrorData as McpError, handler::server::wrapper::Parameters, model::CallToolResult,
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    image_processing, modelscope,
+    tools::{
+        LocatedObject, draw_boxes, filter_min_size, image_data_url, model_image_max_edge,
+        non_max_suppression, parse_colors, persist_processed_png, read_or_download_image,
+        validate_http_url,
+    },
+};
+
+const DEFAULT_IOU_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CountObjectsRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+    #[schemars(description = "需要计数的物体名称")]
+    pub object_name: String,
+    #[schemars(description = "去重的 IoU 阈值（0-1），重叠超过该值的边界框视为同一物体，默认 0.5")]
+    pub iou_threshold: Option<f32>,
+    #[schemars(description = "最小边长（Qwen3 坐标单位，0-999），宽或高小于该值的边界框会被过滤，默认 0")]
+    pub min_size: Option<f32>,
+    #[schemars(description = "是否返回绘制了去重后边界框与序号的标注图，默认 false")]
+    pub annotate: Option<bool>,
+    #[schemars(description = "标注颜色列表（#RRGGBB 或 red、green、blue 等），按序号循环使用")]
+    pub colors: Option<Vec<String>>,
+    #[schemars(description = "标注线宽（像素），默认按图片尺寸自动选择")]
+    pub line_width: Option<u32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CountObjectsOutput {
+    pub image_url: String,
+    pub object_name: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    /// 去重与过滤后的数量
+    pub count: usize,
+    /// 模型原始返回的边界框数量
+    pub raw_count: usize,
+    /// 因尺寸过小被过滤的数量
+    pub removed_small: usize,
+    /// 被非极大值抑制去掉的重复框数量
+    pub removed_duplicates: usize,
+    pub objects: Vec<LocatedObject>,
+    /// annotate 为 true 时返回的标注图地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotated_image_url: Option<String>,
+}
+
+pub async fn count_objects(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<CountObjectsRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    let iou_threshold = request.iou_threshold.unwrap_or(DEFAULT_IOU_THRESHOLD);
+    if !(0.0..=1.0).contains(&iou_threshold) {
+        return Err(McpError::invalid_params(
+            "iou_threshold 取值范围为 [0, 1]",
+            None,
+        ));
+    }
+    let min_size = request.min_size.unwrap_or(0.0);
+    if !(0.0..=999.0).contains(&min_size) {
+        return Err(McpError::invalid_params("min_size 取值范围为 [0, 999]", None));
+    }
+    let colors = parse_colors(request.colors.as_deref())?;
+    let api_key = std::env::var("MODELSCOPE_API_KEY")
+        .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+    if api_key.trim().is_empty() {
+        return Err(McpError::internal_error(
+            "missing MODELSCOPE_API_KEY",
+            None,
+        ));
+    }
+
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let model_image_url = image_data_url(&image.bytes, &image.mime_type, model_image_max_edge())?;
+    // 模型没有找到目标时计数为 0，其余错误原样返回
+    let boxes = modelscope::boxes_or_empty(
+        modelscope::locate_object_with_qwen(&model_image_url, &request.object_name, &api_key)
+            .await,
+    )
+    .map_err(|err| {
+        McpError::internal_error(
+            "locate object failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let raw_count = boxes.len();
+    let boxes = filter_min_size(boxes, min_size);
+    let removed_small = raw_count - boxes.len();
+    let before_nms = boxes.len();
+    let boxes = non_max_suppression(boxes, iou_threshold);
+    let removed_duplicates = before_nms - boxes.len();
+
+    let (mut pixels, width, height) =
+        image_processing::decode_image(&image.bytes, &image.mime_type).map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let rects: Vec<_> = boxes
+        .iter()
+        .map(|bbox| bbox.to_pixel_rect(width, height))
+        .collect();
+
+    let annotated_image_url = if request.annotate.unwrap_or(false) && !boxes.is_empty() {
+        let cache_key_input = format!(
+            "count:{}:{}:{}",
+            validated_url,
+            compute_bytes_hash(&image.bytes),
+            serde_json::json!({
+                "boxes": boxes,
+                "colors": colors,
+                "line_width": request.line_width,
+            })
+        );
+        draw_boxes(&mut pixels, width, height, &rects, &colors, request.line_width);
+        Some(persist_processed_png(storage, &cache_key_input, &pixels, width, height).await?)
+    } else {
+        None
+    };
+
+    let objects: Vec<_> = boxes
+        .iter()
+        .zip(&rects)
+        .enumerate()
+        .map(|(index, (bbox, rect))| {
+            LocatedObject::new(index, bbox, *rect, &request.object_name)
+        })
+        .collect();
+    let output = CountObjectsOutput {
+        image_url: validated_url,
+        object_name: request.object_name,
+        image_width: width,
+        image_height: height,
+        count: objects.len(),
+        raw_count,
+        removed_small,
+        removed_duplicates,
+        objects,
+        annotated_image_url,
+    };
+    let value = serde_json::to_value(&output).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::structured(value))
+}
@@ -1,51 +1,194 @@
 use crate::{
-    cache::{AiImageRecord, LocalFileStorage, save_ai_image_record},
-    modelscope,
-    tools::{ToolResponse, validate_http_url},
+    cache::{AiImageRecord, LocalFileStorage, compute_bytes_hash, save_ai_image_record},
+    image_processing, metadata_strip, modelscope,
+    tools::{
+        DownloadedImage, RegionBox, ToolResponse, download_image,
+        image_size::{
+            QWEN_IMAGE_EDIT_LIMITS, compute_size, format_size, normalize_explicit_size,
+            parse_aspect_ratio, parse_resolution,
+        },
+        persist_edited_image, persist_edited_image_bytes, persist_processed_bytes,
+        read_or_download_image, task_observer, validate_http_url,
+    },
 };
 use anyhow::Result;
 use chrono::Utc;
 use rmcp::{
     ErrorData as McpError,
+    RoleServer,
     handler::server::wrapper::Parameters,
     model::{CallToolResult, Content},
     schemars::JsonSchema,
+    service::RequestContext,
 };
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct EditImageRequest {
-    #[schemars(description = "待编辑图片URL")]
-    pub image_url: String,
+    #[schemars(description = "待编辑图片URL（单张图片时使用）")]
+    pub image_url: Option<String>,
+    #[schemars(description = "待编辑图片URL列表，最多 3 张；第 1 张为主图，其余为参考图，编辑指令中可用“图1”“图2”指代")]
+    pub image_urls: Option<Vec<String>>,
     #[schemars(description = "编辑指令")]
     pub prompt: String,
-    #[schemars(description = "输出图片尺寸")]
+    #[schemars(description = "输出图片尺寸，格式为 宽x高（如 1024x768），超出模型范围 [64, 1664] 时按比例收敛；与 aspect_ratio/resolution/keep_source_aspect 互斥；局部重绘时不可用")]
     pub size: Option<String>,
+    #[schemars(description = "输出图片比例，可选值：1:1、16:9、9:16、4:3、3:4、3:2、2:3。默认 1:1；局部重绘时不可用，输出比例与原图一致")]
+    pub aspect_ratio: Option<String>,
+    #[schemars(description = "输出图片分辨率，可选值：1k、2k、4k。默认 1k，超出模型上限时自动降级")]
+    pub resolution: Option<String>,
+    #[schemars(description = "是否按第 1 张源图片的宽高比输出，默认 false；与 aspect_ratio 互斥")]
+    pub keep_source_aspect: Option<bool>,
     #[schemars(description = "采样步数")]
     pub steps: Option<u32>,
+    #[schemars(description = "局部重绘蒙版图片URL，白色区域为需要编辑的部分，仅作用于第 1 张图片；与 mask_box 二选一")]
+    pub mask_url: Option<String>,
+    #[schemars(description = "局部重绘区域（Qwen3 坐标，0-999），仅作用于第 1 张图片；与 mask_url 二选一")]
+    pub mask_box: Option<RegionBox>,
 }
 
+/// 局部重绘所需的原图像素与单通道蒙版
+struct InpaintInput {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    mask: Vec<u8>,
+}
+
+const MAX_SOURCE_IMAGES: usize = 3;
+
 pub async fn edit_image(
-    _storage: &LocalFileStorage,
+    storage: &LocalFileStorage,
     Parameters(request): Parameters<EditImageRequest>,
+    context: RequestContext<RoleServer>,
 ) -> Result<CallToolResult, McpError> {
-    let validated_url = validate_http_url(&request.image_url)?;
-    let validated_url = validated_url.to_string();
+    let raw_urls: Vec<&String> = request
+        .image_url
+        .iter()
+        .chain(request.image_urls.iter().flatten())
+        .collect();
+    if raw_urls.is_empty() {
+        return Err(McpError::invalid_params(
+            "image_url 或 image_urls 不能为空",
+            None,
+        ));
+    }
+    if raw_urls.len() > MAX_SOURCE_IMAGES {
+        return Err(McpError::invalid_params(
+            format!("最多支持 {MAX_SOURCE_IMAGES} 张源图片"),
+            None,
+        ));
+    }
+    let mut source_image_urls = Vec::with_capacity(raw_urls.len());
+    let mut sources = Vec::with_capacity(raw_urls.len());
+    let mut model_image_urls = Vec::with_capacity(raw_urls.len());
+    for raw_url in raw_urls {
+        let validated_url = validate_http_url(raw_url)?.to_string();
+        let source = read_or_download_image(storage, &validated_url).await?;
+        model_image_urls.push(model_source_url(storage, &validated_url, &source).await?);
+        sources.push(source);
+        source_image_urls.push(validated_url);
+    }
+    let keep_source_aspect = request.keep_source_aspect.unwrap_or(false);
+    if request.size.is_some()
+        && (request.aspect_ratio.is_some() || request.resolution.is_some() || keep_source_aspect)
+    {
+        return Err(McpError::invalid_params(
+            "size 不能与 aspect_ratio、resolution、keep_source_aspect 同时使用",
+            None,
+        ));
+    }
+    if keep_source_aspect && request.aspect_ratio.is_some() {
+        return Err(McpError::invalid_params(
+            "keep_source_aspect 不能与 aspect_ratio 同时使用",
+            None,
+        ));
+    }
+    let explicit_size = request
+        .size
+        .as_deref()
+        .map(|size| normalize_explicit_size(size, QWEN_IMAGE_EDIT_LIMITS))
+        .transpose()?;
+    let base = parse_resolution(
+        request.resolution.as_deref().unwrap_or("1k"),
+        QWEN_IMAGE_EDIT_LIMITS,
+    )?;
+    let ratio = parse_aspect_ratio(request.aspect_ratio.as_deref().unwrap_or("1:1"))?;
+    if request.mask_url.is_some() && request.mask_box.is_some() {
+        return Err(McpError::invalid_params(
+            "mask_url 与 mask_box 只能提供一个",
+            None,
+        ));
+    }
+    // 局部重绘的结果需要与原图逐像素合成，输出比例固定为原图比例
+    if (request.mask_url.is_some() || request.mask_box.is_some())
+        && (request.size.is_some() || request.aspect_ratio.is_some())
+    {
+        return Err(McpError::invalid_params(
+            "使用 mask_url 或 mask_box 时输出比例与原图一致，不能指定 size 或 aspect_ratio",
+            None,
+        ));
+    }
+    // 先准备原图与蒙版，避免参数错误时白白消耗生成额度
+    let inpaint = if request.mask_url.is_some() || request.mask_box.is_some() {
+        Some(
+            prepare_inpaint(&sources[0], request.mask_url.as_deref(), request.mask_box).await?,
+        )
+    } else {
+        None
+    };
     let api_key = std::env::var("MODELSCOPE_API_KEY")
         .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
     if api_key.trim().is_empty() {
         return Err(McpError::internal_error("missing MODELSCOPE_API_KEY", None));
     }
-    let prompt = request.prompt.clone();
-    let source_image_url = validated_url.clone();
-    let size = request.size.clone();
+    let model_prompt = match &inpaint {
+        Some(inpaint) => format!("{}{}", request.prompt, region_hint(inpaint)),
+        None => request.prompt.clone(),
+    };
+    let size = if let Some(explicit_size) = explicit_size {
+        Some(format_size(explicit_size))
+    } else if let Some(inpaint) = &inpaint {
+        Some(format_size(compute_size(
+            inpaint.width as f64,
+            inpaint.height as f64,
+            base,
+            QWEN_IMAGE_EDIT_LIMITS,
+        )))
+    } else if keep_source_aspect {
+        let (width, height) =
+            image_processing::get_dimensions(&sources[0].bytes, &sources[0].mime_type).map_err(
+                |err| {
+                    McpError::internal_error(
+                        "decode image failed",
+                        Some(serde_json::Value::String(err.to_string())),
+                    )
+                },
+            )?;
+        Some(format_size(compute_size(
+            width as f64,
+            height as f64,
+            base,
+            QWEN_IMAGE_EDIT_LIMITS,
+        )))
+    } else if request.aspect_ratio.is_some() || request.resolution.is_some() {
+        Some(format_size(compute_size(
+            ratio.0,
+            ratio.1,
+            base,
+            QWEN_IMAGE_EDIT_LIMITS,
+        )))
+    } else {
+        None
+    };
     let steps = request.steps;
-    let image_url = modelscope::edit_image_with_qwen(
-        &validated_url,
-        &request.prompt,
-        request.size.as_deref(),
+    let result = modelscope::edit_image_with_qwen(
+        &model_image_urls,
+        &model_prompt,
+        size.as_deref(),
         request.steps,
         &api_key,
+        &task_observer(&context, 1),
     )
     .await
     .map_err(|err| {
@@ -55,24 +198,73 @@ pub async fn edit_image(
         )
     })?;
 
+    let upstream_image_url = result
+        .image_urls
+        .into_iter()
+        .next()
+        .ok_or_else(|| McpError::internal_error("edit image returned no output", None))?;
+    let (image_url, mime_type, text) = match inpaint {
+        Some(inpaint) => {
+            let cache_key_input = format!("edited:{}:inpaint", result.task_id);
+            let composited = composite_inpaint(&inpaint, &upstream_image_url).await?;
+            let metadata = persist_edited_image_bytes(
+                storage,
+                &cache_key_input,
+                &upstream_image_url,
+                &composited,
+                "image/png",
+            )
+            .await?;
+            (
+                metadata.cached_image_url,
+                metadata.mime_type,
+                "图像已局部编辑，蒙版以外的像素保持不变。".to_string(),
+            )
+        }
+        None => {
+            let cache_key_input = format!("edited:{}", result.task_id);
+            match persist_edited_image(storage, &cache_key_input, &upstream_image_url).await {
+                Ok(metadata) => (
+                    metadata.cached_image_url,
+                    metadata.mime_type,
+                    "图像已编辑。".to_string(),
+                ),
+                Err(err) => {
+                    eprintln!(
+                        "[WARN] edit_image: cache output failed, falling back to upstream url: {:?}",
+                        err
+                    );
+                    (
+                        upstream_image_url.clone(),
+                        "image/png".to_string(),
+                        "图像已编辑（缓存失败，返回的是魔搭临时地址，可能会过期）。".to_string(),
+                    )
+                }
+            }
+        }
+    };
+
     let record = AiImageRecord {
         image_url: image_url.clone(),
         image_type: "edited".to_string(),
-        prompt,
+        prompt: request.prompt,
         negative_prompt: None,
-        aspect_ratio: None,
+        aspect_ratio: request.aspect_ratio,
         resolution: size,
         steps,
-        source_image_url: Some(source_image_url),
+        seed: None,
+        loras: None,
+        source_image_url: source_image_urls.first().cloned(),
+        source_image_urls,
         created_at: Utc::now().to_rfc3339(),
     };
-    let _ = save_ai_image_record(_storage, &record).await;
+    let _ = save_ai_image_record(storage, &record).await;
 
     let response = ToolResponse {
         url: image_url,
         name: "edited-image".to_string(),
-        mime_type: "image/png".to_string(),
-        text: "图像已编辑。".to_string(),
+        mime_type,
+        text,
     };
     let json = serde_json::to_string(&response).map_err(|err| {
         McpError::internal_error(
@@ -82,3 +274,206 @@ pub async fn edit_image(
     })?;
     Ok(CallToolResult::success(vec![Content::text(json)]))
 }
+
+/// 编辑接口只接受公网 URL 且不处理 EXIF 方向：无需摆正时直接发送原图地址，
+/// 否则发送摆正后副本的 `/cache` 地址
+async fn model_source_url(
+    storage: &LocalFileStorage,
+    validated_url: &str,
+    source: &DownloadedImage,
+) -> Result<String, McpError> {
+    if !image_processing::needs_reorientation(&source.bytes, &source.mime_type) {
+        return Ok(validated_url.to_string());
+    }
+    let oriented = metadata_strip::strip_metadata(&source.bytes, &source.mime_type).map_err(
+        |err| {
+            McpError::internal_error(
+                "orient image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        },
+    )?;
+    let cache_key_input = format!(
+        "orient:{}:{}",
+        validated_url,
+        compute_bytes_hash(&source.bytes)
+    );
+    persist_processed_bytes(storage, &cache_key_input, &oriented.bytes, &oriented.mime_type).await
+}
+
+async fn prepare_inpaint(
+    source: &DownloadedImage,
+    mask_url: Option<&str>,
+    mask_box: Option<RegionBox>,
+) -> Result<InpaintInput, McpError> {
+    let (pixels, width, height) = image_processing::decode_image(&source.bytes, &source.mime_type)
+        .map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let mask = match (mask_url, mask_box) {
+        (Some(mask_url), _) => {
+            let mask_url = validate_http_url(mask_url)?.to_string();
+            let mask_image = download_image(&mask_url).await?;
+            let (mask_pixels, mask_width, mask_height) =
+                image_processing::decode_image(&mask_image.bytes, &mask_image.mime_type).map_err(
+                    |err| {
+                        McpError::internal_error(
+                            "decode mask failed",
+                            Some(serde_json::Value::String(err.to_string())),
+                        )
+                    },
+                )?;
+            let mask_pixels = image_processing::resize_pixels(
+                &mask_pixels,
+                mask_width,
+                mask_height,
+                width,
+                height,
+            )
+            .map_err(|err| {
+                McpError::internal_error(
+                    "resize mask failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            image_processing::mask_from_pixels(&mask_pixels, width, height).map_err(|err| {
+                McpError::internal_error(
+                    "decode mask failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?
+        }
+        (None, Some(mask_box)) => {
+            mask_box.validate()?;
+            let rect = mask_box
+                .to_pixel_rect(width, height)
+                .ok_or_else(|| McpError::invalid_params("mask_box 区域为空", None))?;
+            image_processing::rasterize_rect_mask(width, height, rect)
+        }
+        (None, None) => return Err(McpError::invalid_params("缺少蒙版", None)),
+    };
+    if !mask.iter().any(|value| *value != 0) {
+        return Err(McpError::invalid_params("蒙版中没有需要编辑的区域", None));
+    }
+    Ok(InpaintInput {
+        pixels,
+        width,
+        height,
+        mask,
+    })
+}
+
+/// 根据蒙版外接矩形生成区域提示，帮助模型只改动目标区域
+fn region_hint(inpaint: &InpaintInput) -> String {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (inpaint.width, inpaint.height, 0, 0);
+    for (index, value) in inpaint.mask.iter().enumerate() {
+        if *value == 0 {
+            continue;
+        }
+        let x = index as u32 % inpaint.width;
+        let y = index as u32 / inpaint.width;
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let percent = |value: u32, total: u32| value as u64 * 100 / total.max(1) as u64;
+    format!(
+        "\n\n仅修改图像中横向 {}%-{}%、纵向 {}%-{}% 范围内的内容，其余部分保持不变。",
+        percent(min_x, inpaint.width),
+        percent(max_x + 1, inpaint.width),
+        percent(min_y, inpaint.height),
+        percent(max_y + 1, inpaint.height)
+    )
+}
+
+/// 下载模型输出并按蒙版合成回原图
+async fn composite_inpaint(
+    inpaint: &InpaintInput,
+    upstream_image_url: &str,
+) -> Result<Vec<u8>, McpError> {
+    let edited = download_image(upstream_image_url).await?;
+    let (edited_pixels, edited_width, edited_height) =
+        image_processing::decode_image(&edited.bytes, &edited.mime_type).map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let composited = composite_edited_pixels(inpaint, &edited_pixels, edited_width, edited_height)?;
+    image_processing::encode_png(&composited, inpaint.width, inpaint.height).map_err(|err| {
+        McpError::internal_error(
+            "encode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })
+}
+
+/// 模型输出与原图比例一致（请求尺寸按原图比例计算），仅需缩放到原图尺寸后合成
+fn composite_edited_pixels(
+    inpaint: &InpaintInput,
+    edited_pixels: &[u8],
+    edited_width: u32,
+    edited_height: u32,
+) -> Result<Vec<u8>, McpError> {
+    let edited_pixels = image_processing::resize_pixels(
+        edited_pixels,
+        edited_width,
+        edited_height,
+        inpaint.width,
+        inpaint.height,
+    )
+    .map_err(|err| {
+        McpError::internal_error(
+            "resize image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    image_processing::composite_with_mask(&inpaint.pixels, &edited_pixels, &inpaint.mask).map_err(
+        |err| {
+            McpError::internal_error(
+                "composite image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composite_edited_pixels_keeps_unmasked_source_pixels() {
+        // 4x2 原图，蒙版覆盖右半部分
+        let inpaint = InpaintInput {
+            pixels: [10u8; 4 * 2 * 4].to_vec(),
+            width: 4,
+            height: 2,
+            mask: image_processing::rasterize_rect_mask(4, 2, (2, 0, 2, 2)),
+        };
+        // 模型输出为 2 倍尺寸的纯色图
+        let edited = [200u8; 8 * 4 * 4];
+        let output = composite_edited_pixels(&inpaint, &edited, 8, 4).unwrap();
+        assert_eq!(output.len(), inpaint.pixels.len());
+        for (index, pixel) in output.chunks_exact(4).enumerate() {
+            let expected = if index % 4 >= 2 { 200 } else { 10 };
+            assert_eq!(pixel, [expected; 4], "pixel {index}");
+        }
+    }
+
+    #[test]
+    fn composite_edited_pixels_rejects_invalid_buffers() {
+        let inpaint = InpaintInput {
+            pixels: [10u8; 16].to_vec(),
+            width: 2,
+            height: 2,
+            mask: vec![255; 3],
+        };
+        assert!(composite_edited_pixels(&inpaint, &[0u8; 16], 2, 2).is_err());
+        assert!(composite_edited_pixels(&inpaint, &[0u8; 15], 2, 2).is_err());
+    }
+}
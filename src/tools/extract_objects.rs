@@ -0,0 +1,174 @@
+use rmcp::{
+    ErrorData as McpError, handler::server::wrapper::Parameters, model::CallToolResult,
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    image_processing, modelscope,
+    tools::{
+        NormalizedBox, PixelBox, image_data_url, model_image_max_edge, persist_processed_png,
+        read_or_download_image, validate_http_url,
+    },
+};
+
+const MAX_MARGIN: f32 = 1.0;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExtractObjectsRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+    #[schemars(description = "需要提取的物体名称")]
+    pub object_name: String,
+    #[schemars(
+        description = "按边界框宽高比例向外扩展的边距（0-1），例如 0.1 表示四边各扩展 10%，默认 0"
+    )]
+    pub margin: Option<f32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExtractedObject {
+    /// 序号（从 1 开始，与 locate_object 的顺序一致）
+    pub index: usize,
+    /// 目标标签，模型未给出时为请求的物体名称
+    pub label: String,
+    /// 模型给出的置信度（0-1），未给出时为 null
+    pub confidence: Option<f32>,
+    /// 模型返回的原始边界框（Qwen3 归一化坐标，0-999）
+    pub normalized: NormalizedBox,
+    /// 扩展边距后实际裁剪的区域（原图像素坐标）
+    pub crop: PixelBox,
+    /// 裁剪结果的缓存地址（PNG）
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExtractObjectsOutput {
+    pub image_url: String,
+    pub object_name: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub margin: f32,
+    pub count: usize,
+    pub objects: Vec<ExtractedObject>,
+}
+
+pub async fn extract_objects(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<ExtractObjectsRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    let margin = request.margin.unwrap_or(0.0);
+    if !(0.0..=MAX_MARGIN).contains(&margin) {
+        return Err(McpError::invalid_params("margin 取值范围为 [0, 1]", None));
+    }
+    let api_key = std::env::var("MODELSCOPE_API_KEY")
+        .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+    if api_key.trim().is_empty() {
+        return Err(McpError::internal_error(
+            "missing MODELSCOPE_API_KEY",
+            None,
+        ));
+    }
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let model_image_url = image_data_url(&image.bytes, &image.mime_type, model_image_max_edge())?;
+    let boxes = modelscope::locate_object_with_qwen(
+        &model_image_url,
+        &request.object_name,
+        &api_key,
+    )
+    .await
+    .map_err(|err| {
+        McpError::internal_error(
+            "locate object failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+
+    // 只解码一次，所有目标都从同一份像素中裁剪
+    let (pixels, width, height) = image_processing::decode_image(&image.bytes, &image.mime_type)
+        .map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+
+    let content_hash = compute_bytes_hash(&image.bytes);
+    let mut objects = Vec::with_capacity(boxes.len());
+    for (index, bbox) in boxes.iter().enumerate() {
+        let Some(rect) = bbox.to_pixel_rect(width, height) else {
+            continue;
+        };
+        let rect = pad_rect(rect, margin, width, height);
+        let cropped = image_processing::crop_rect(&pixels, width, height, rect).map_err(|err| {
+            McpError::internal_error(
+                "crop image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        let cache_key_input = format!(
+            "extract:{}:{}:{}:{}:{}:{}",
+            validated_url, content_hash, rect.0, rect.1, rect.2, rect.3
+        );
+        let url = persist_processed_png(storage, &cache_key_input, &cropped, rect.2, rect.3).await?;
+        objects.push(ExtractedObject {
+            index: index + 1,
+            label: bbox
+                .label
+                .clone()
+                .unwrap_or_else(|| request.object_name.clone()),
+            confidence: bbox.confidence,
+            normalized: NormalizedBox::from(bbox),
+            crop: PixelBox::from_rect(rect),
+            url,
+        });
+    }
+
+    let output = ExtractObjectsOutput {
+        image_url: validated_url,
+        object_name: request.object_name,
+        image_width: width,
+        image_height: height,
+        margin,
+        count: objects.len(),
+        objects,
+    };
+    let value = serde_json::to_value(&output).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::structured(value))
+}
+
+/// 按宽高比例向四周扩展像素矩形，并收敛到图片范围内
+pub fn pad_rect(
+    (x, y, rect_width, rect_height): (u32, u32, u32, u32),
+    margin: f32,
+    width: u32,
+    height: u32,
+) -> (u32, u32, u32, u32) {
+    let pad_x = (rect_width as f32 * margin).round() as u32;
+    let pad_y = (rect_height as f32 * margin).round() as u32;
+    let x1 = x.saturating_sub(pad_x);
+    let y1 = y.saturating_sub(pad_y);
+    let x2 = (x + rect_width).saturating_add(pad_x).min(width);
+    let y2 = (y + rect_height).saturating_add(pad_y).min(height);
+    (x1, y1, x2 - x1, y2 - y1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad_rect_expands_and_clamps_to_image() {
+        assert_eq!(pad_rect((100, 100, 50, 20), 0.1, 1000, 1000), (95, 98, 60, 24));
+        assert_eq!(pad_rect((0, 0, 50, 20), 0.5, 60, 25), (0, 0, 60, 25));
+        assert_eq!(pad_rect((10, 10, 5, 5), 0.0, 100, 100), (10, 10, 5, 5));
+    }
+}
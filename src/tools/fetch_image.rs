@@ -5,7 +5,7 @@ use rmcp::{
     model::{CallToolResult, Content},
     schemars::JsonSchema,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tokio::task::JoinSet;
 
 use chrono::Utc;
@@ -16,9 +16,12 @@ use crate::{
         LocalFileStorage,
         compute_hash,
     },
-    image_processing,
-    modelscope,
-    tools::{ToolResponse, validate_http_url},
+    image_processing::{self, ImageProfile},
+    modelscope::{self, DescriptionDetail, DescriptionExtras, DescriptionField, DescriptionOptions},
+    tools::{
+        ToolResponse, image_data_url, model_image_max_edge, read_or_download_image,
+        validate_http_url,
+    },
 };
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -27,17 +30,21 @@ pub struct FetchImageRequest {
     pub urls: Vec<String>,
     #[schemars(description = "需要关注的重要内容")]
     pub focus: Option<String>,
+    #[schemars(description = "描述使用的语言，如 English、日本語，默认中文")]
+    pub language: Option<String>,
+    #[schemars(description = "描述详略：brief（一句话）、normal（默认）、exhaustive（详尽）")]
+    pub detail: Option<DescriptionDetail>,
+    #[schemars(
+        description = "额外返回的字段：tags（标签）、dominant_objects（主要物体）、text_present（是否含文字）、nsfw（是否不适宜公开）"
+    )]
+    pub extra_fields: Option<Vec<DescriptionField>>,
+    #[schemars(
+        description = "是否调用视觉模型生成描述，默认 true；为 false 时只返回格式、尺寸、颜色类型、位深、帧数、EXIF 等技术信息"
+    )]
+    pub describe: Option<bool>,
 }
 
-#[derive(Debug, Serialize, JsonSchema)]
-struct ImageInfo {
-    pub width: u32,
-    pub height: u32,
-    pub total_pixels: u64,
-    pub mime_type: String,
-    pub size: usize,
-    pub aspect_ratio: Option<f64>,
-}
+const MAX_LANGUAGE_LEN: usize = 32;
 
 pub async fn fetch_image(
     storage: &LocalFileStorage,
@@ -47,15 +54,46 @@ pub async fn fetch_image(
         return Err(McpError::invalid_params("urls不能为空", None));
     }
 
+    let language = request
+        .language
+        .as_deref()
+        .map(str::trim)
+        .filter(|language| !language.is_empty())
+        .map(str::to_string);
+    if language
+        .as_ref()
+        .is_some_and(|language| language.chars().count() > MAX_LANGUAGE_LEN)
+    {
+        return Err(McpError::invalid_params(
+            format!("language 不能超过 {MAX_LANGUAGE_LEN} 个字符"),
+            None,
+        ));
+    }
+    let mut extra_fields = request.extra_fields.unwrap_or_default();
+    extra_fields.sort();
+    extra_fields.dedup();
+    let options = DescriptionOptions {
+        focus: request
+            .focus
+            .as_deref()
+            .map(str::trim)
+            .filter(|focus| !focus.is_empty())
+            .map(str::to_string),
+        language,
+        detail: request.detail.unwrap_or_default(),
+        extra_fields,
+    };
+
+    let describe = request.describe.unwrap_or(true);
+
     let total = request.urls.len();
-    let focus = request.focus.clone();
     let mut join_set = JoinSet::new();
 
     for (index, url) in request.urls.into_iter().enumerate() {
         let storage = storage.clone();
-        let focus = focus.clone();
+        let options = options.clone();
         join_set.spawn(async move {
-            let result = fetch_single_image(&storage, &url, focus.as_deref()).await;
+            let result = fetch_single_image(&storage, &url, &options, describe).await;
             (index, result)
         });
     }
@@ -99,122 +137,80 @@ pub async fn fetch_image(
 async fn fetch_single_image(
     storage: &LocalFileStorage,
     raw_url: &str,
-    focus: Option<&str>,
+    options: &DescriptionOptions,
+    describe: bool,
 ) -> Result<ToolResponse, McpError> {
     let validated_url = validate_http_url(raw_url)?;
     let validated_url = validated_url.to_string();
-    let cache_key_input = match focus {
-        Some(focus) if !focus.trim().is_empty() => {
-            format!("{}::{}", validated_url, focus.trim())
-        }
-        _ => validated_url.clone(),
-    };
+    let cache_key_input = description_cache_key(&validated_url, options);
     let hash = compute_hash(&cache_key_input);
     let prefix = LocalFileStorage::get_image_prefix(&hash);
     let meta_key = LocalFileStorage::get_meta_key(&prefix);
-    if let Ok(Some(meta_bytes)) = storage.get(&meta_key).await {
-        if let Ok(metadata) = serde_json::from_slice::<ImageCacheMetadata>(&meta_bytes) {
-            let width = metadata.width.unwrap_or_default();
-            let height = metadata.height.unwrap_or_default();
-            let info = ImageInfo {
-                width,
-                height,
-                total_pixels: (width as u64).saturating_mul(height as u64),
-                mime_type: metadata.mime_type.clone(),
-                size: metadata.size.unwrap_or_default(),
-                aspect_ratio: metadata.aspect_ratio,
-            };
-            let info_json = serde_json::to_string(&info).map_err(|err| {
-                McpError::internal_error(
-                    "serialize image info failed",
-                    Some(serde_json::Value::String(err.to_string())),
-                )
-            })?;
-            return Ok(ToolResponse {
-                url: metadata.original_url,
-                name: metadata.name,
-                mime_type: metadata.mime_type,
-                text: format!("{}\n\n图像信息: {}", metadata.description, info_json),
-            });
-        }
+    // 旧缓存没有技术信息，视为未命中重新抓取；无法解析的格式本就没有技术信息
+    if describe
+        && let Ok(Some(meta_bytes)) = storage.get(&meta_key).await
+        && let Ok(metadata) = serde_json::from_slice::<ImageCacheMetadata>(&meta_bytes)
+        && (metadata.profile.is_some()
+            || image_processing::mime_to_format(&metadata.mime_type).is_err())
+    {
+        let info_json = info_json(
+            metadata.profile.as_ref(),
+            &metadata.mime_type,
+            metadata.size.unwrap_or_default(),
+        )?;
+        return Ok(ToolResponse {
+            url: metadata.original_url,
+            name: metadata.name,
+            mime_type: metadata.mime_type,
+            text: response_text(&metadata.description, &info_json, &metadata.extras)?,
+        });
     }
-    let response = reqwest::get(&validated_url).await.map_err(|err| {
-        McpError::internal_error(
-            "fetch image failed",
-            Some(serde_json::Value::String(err.to_string())),
-        )
-    })?;
-    let status = response.status();
-    if !status.is_success() {
-        return Err(McpError::internal_error(
-            "fetch image failed",
-            Some(serde_json::Value::String(format!("HTTP {status}"))),
-        ));
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let bytes = image.bytes;
+    let mime_type = image.mime_type;
+
+    // svg、avif、heic 等无法解码的格式仍然返回描述，只是没有技术信息
+    let profile = match image_processing::inspect_image(&bytes, &mime_type) {
+        Ok(profile) => Some(profile),
+        Err(err) => {
+            eprintln!("[WARN] fetch_image: inspect {validated_url} failed: {err}");
+            None
+        }
+    };
+    let info_json = info_json(profile.as_ref(), &mime_type, bytes.len())?;
+    if !describe {
+        return Ok(ToolResponse {
+            url: validated_url,
+            name: "fetched-image".to_string(),
+            mime_type,
+            text: format!("图像信息: {}", info_json),
+        });
     }
-    let headers = response.headers().clone();
-    let bytes = response.bytes().await.map_err(|err| {
-        McpError::internal_error(
-            "read image bytes failed",
-            Some(serde_json::Value::String(err.to_string())),
-        )
-    })?;
-    let mime_from_header = headers
-        .get(reqwest::header::CONTENT_TYPE)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
-    let detected = image_processing::detect_mime_type(bytes.as_ref()).map(str::to_string);
-    let mime_type = detected
-        .or(mime_from_header)
-        .ok_or_else(|| McpError::internal_error("unsupported image type", None))?;
+
+    // 描述失败不影响抓取结果，编码失败时退回让模型拉取原始 URL
+    let model_image_url = image_data_url(&bytes, &mime_type, model_image_max_edge())
+        .unwrap_or_else(|_| validated_url.clone());
 
     let mut title = "Fetched Image".to_string();
     let mut description = "请分析图片内容。".to_string();
     let mut name = "fetched-image".to_string();
+    let mut extras = DescriptionExtras::default();
 
-    if let Ok(api_key) = std::env::var("MODELSCOPE_API_KEY") {
-        if !api_key.trim().is_empty() {
-            if let Ok((desc_name, desc_text)) = modelscope::describe_image_with_qwen(
-                &validated_url,
-                &api_key,
-                focus,
-            )
-            .await
-            {
-                if !desc_name.trim().is_empty() {
-                    name = desc_name.trim().to_string();
-                    title = name.clone();
-                }
-                if !desc_text.trim().is_empty() {
-                    description = desc_text.trim().to_string();
-                }
-            }
+    if let Ok(api_key) = std::env::var("MODELSCOPE_API_KEY")
+        && !api_key.trim().is_empty()
+        && let Ok(described) =
+            modelscope::describe_image_with_qwen(&model_image_url, &api_key, options).await
+    {
+        if !described.name.trim().is_empty() {
+            name = described.name.trim().to_string();
+            title = name.clone();
+        }
+        if !described.description.trim().is_empty() {
+            description = described.description.trim().to_string();
         }
+        extras = described.extras;
     }
 
-    let (width, height, aspect_ratio) = image_processing::get_dimensions(bytes.as_ref(), &mime_type)
-        .map(|(width, height)| {
-            let aspect_ratio = if height == 0 {
-                None
-            } else {
-                Some(width as f64 / height as f64)
-            };
-            (width, height, aspect_ratio)
-        })
-        .unwrap_or((0, 0, None));
-    let info = ImageInfo {
-        width,
-        height,
-        total_pixels: (width as u64).saturating_mul(height as u64),
-        mime_type: mime_type.clone(),
-        size: bytes.len(),
-        aspect_ratio,
-    };
-    let info_json = serde_json::to_string(&info).map_err(|err| {
-        McpError::internal_error(
-            "serialize image info failed",
-            Some(serde_json::Value::String(err.to_string())),
-        )
-    })?;
     let metadata = ImageCacheMetadata {
         original_url: validated_url.clone(),
         mime_type: mime_type.clone(),
@@ -222,10 +218,12 @@ async fn fetch_single_image(
         title: title.clone(),
         description: description.clone(),
         created_at: Utc::now().to_rfc3339(),
-        width: if width == 0 { None } else { Some(width) },
-        height: if height == 0 { None } else { Some(height) },
+        width: profile.as_ref().map(|profile| profile.width),
+        height: profile.as_ref().map(|profile| profile.height),
         size: Some(bytes.len()),
-        aspect_ratio,
+        aspect_ratio: profile.as_ref().and_then(|profile| profile.aspect_ratio),
+        extras: extras.clone(),
+        profile,
     };
     let meta_json = serde_json::to_vec(&metadata).map_err(|err| {
         McpError::internal_error(
@@ -244,6 +242,67 @@ async fn fetch_single_image(
         url: validated_url,
         name,
         mime_type,
-        text: format!("{}\n\n图像信息: {}", description, info_json),
+        text: response_text(&description, &info_json, &extras)?,
     })
 }
+
+/// 默认选项沿用旧的缓存键，避免已有缓存失效
+fn description_cache_key(validated_url: &str, options: &DescriptionOptions) -> String {
+    let mut cache_key_input = validated_url.to_string();
+    if let Some(focus) = &options.focus {
+        cache_key_input.push_str(&format!("::{focus}"));
+    }
+    if options.language.is_some()
+        || options.detail != DescriptionDetail::Normal
+        || !options.extra_fields.is_empty()
+    {
+        cache_key_input.push_str(&format!(
+            "::{}",
+            serde_json::json!({
+                "language": options.language,
+                "detail": options.detail,
+                "extra_fields": options.extra_fields,
+            })
+        ));
+    }
+    cache_key_input
+}
+
+/// 无法解析的格式只返回 MIME 类型与文件大小
+fn info_json(
+    profile: Option<&ImageProfile>,
+    mime_type: &str,
+    size: usize,
+) -> Result<String, McpError> {
+    let result = match profile {
+        Some(profile) => serde_json::to_string(profile),
+        None => serde_json::to_string(&serde_json::json!({
+            "mime_type": mime_type,
+            "size": size,
+        })),
+    };
+    result.map_err(|err| {
+        McpError::internal_error(
+            "serialize image info failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })
+}
+
+fn response_text(
+    description: &str,
+    info_json: &str,
+    extras: &DescriptionExtras,
+) -> Result<String, McpError> {
+    let mut text = format!("{}\n\n图像信息: {}", description, info_json);
+    if !extras.is_empty() {
+        let extras_json = serde_json::to_string(extras).map_err(|err| {
+            McpError::internal_error(
+                "serialize description extras failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        text.push_str(&format!("\n\n附加信息: {}", extras_json));
+    }
+    Ok(text)
+}
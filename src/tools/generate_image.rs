@@ -1,16 +1,28 @@
 use anyhow::Result;
 use rmcp::{
     ErrorData as McpError,
+    RoleServer,
     handler::server::wrapper::Parameters,
     model::{CallToolResult, Content},
     schemars::JsonSchema,
+    service::RequestContext,
 };
 use serde::Deserialize;
 use chrono::Utc;
+use tokio::task::JoinSet;
 use crate::{
-    cache::{AiImageRecord, LocalFileStorage, save_ai_image_record},
-    modelscope::{self, GenerateImageOptions},
-    tools::ToolResponse,
+    cache::{
+        AiImageRecord, GeneratedImageCacheMetadata, LocalFileStorage, compute_hash,
+        save_ai_image_record,
+    },
+    modelscope::{self, GenerateImageOptions, Loras, TaskObserver},
+    tools::{
+        ToolResponse,
+        image_size::{
+            Z_IMAGE_TURBO_LIMITS, compute_size, format_size, parse_aspect_ratio, parse_resolution,
+        },
+        persist_generated_image, task_observer,
+    },
 };
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -25,11 +37,27 @@ pub struct GenerateImageRequest {
     pub resolution: Option<String>,
     #[schemars(description = "采样步数")]
     pub steps: Option<u32>,
+    #[schemars(description = "随机种子，取值 0-2147483647。固定种子可复现同一张图")]
+    pub seed: Option<u32>,
+    #[schemars(description = "提示词引导系数，取值 1.5-20")]
+    pub guidance: Option<f32>,
+    #[schemars(description = "生成图片数量，取值 1-4，默认 1")]
+    pub n: Option<u32>,
+    #[schemars(description = "LoRA 模型：单个传仓库 ID 字符串；多个传 {仓库ID: 权重} 对象，最多 6 个且权重之和为 1.0")]
+    pub loras: Option<Loras>,
+    #[schemars(description = "是否复用相同模型、提示词、尺寸、步数、种子与引导系数的历史生成结果，默认 false；建议同时指定 seed")]
+    pub use_cache: Option<bool>,
 }
 
+const MAX_SEED: u32 = i32::MAX as u32;
+const MIN_GUIDANCE: f32 = 1.5;
+const MAX_GUIDANCE: f32 = 20.0;
+const MAX_IMAGE_COUNT: u32 = 4;
+
 pub async fn generate_image(
-    _storage: &LocalFileStorage,
+    storage: &LocalFileStorage,
     Parameters(request): Parameters<GenerateImageRequest>,
+    context: RequestContext<RoleServer>,
 ) -> Result<CallToolResult, McpError> {
     let aspect_ratio = request
         .aspect_ratio
@@ -41,57 +69,11 @@ pub async fn generate_image(
         .as_deref()
         .unwrap_or("1k")
         .trim();
-    // Z-Image-Turbo 模型的分辨率范围是 [512x512, 2048x2048]
-    // 需要确保宽度和高度都不超过最大限制
-    const MAX_DIMENSION: f64 = 2048.0;
-    
-    let base: f64 = match resolution.to_ascii_lowercase().as_str() {
-        "1k" => 1024.0,
-        "2k" => 2048.0,
-        "4k" => 2048.0, // Z-Image-Turbo 最大支持 2048，4k 降级为 2k
-        _ => {
-            return Err(McpError::invalid_params(
-                "resolution 仅支持 1k、2k、4k",
-                None,
-            ))
-        }
-    };
-    let (ratio_w, ratio_h): (f64, f64) = match aspect_ratio {
-        "1:1" => (1.0, 1.0),
-        "16:9" => (16.0, 9.0),
-        "9:16" => (9.0, 16.0),
-        "4:3" => (4.0, 3.0),
-        "3:4" => (3.0, 4.0),
-        "3:2" => (3.0, 2.0),
-        "2:3" => (2.0, 3.0),
-        _ => {
-            return Err(McpError::invalid_params(
-                "aspect_ratio 仅支持 1:1、16:9、9:16、4:3、3:4、3:2、2:3",
-                None,
-            ))
-        }
-    };
-    
-    // 计算尺寸，确保不超过最大限制
-    let (width, height) = {
-        // 先按照 base 计算
-        let scale = base / ratio_w.max(ratio_h);
-        let mut w = (ratio_w * scale).round();
-        let mut h = (ratio_h * scale).round();
-        
-        // 如果任一维度超过最大限制，按比例缩小
-        if w > MAX_DIMENSION || h > MAX_DIMENSION {
-            let scale_down = MAX_DIMENSION / w.max(h);
-            w = (w * scale_down).round();
-            h = (h * scale_down).round();
-        }
-        
-        (w as u32, h as u32)
-    };
-    
-    // 注意：API 文档示例使用 'x' 作为分隔符，如 "1024x1024"
-    let size = format!("{}x{}", width, height);
-    
+    // Z-Image-Turbo 最大支持 2048，4k 降级为 2k
+    let base = parse_resolution(resolution, Z_IMAGE_TURBO_LIMITS)?;
+    let (ratio_w, ratio_h) = parse_aspect_ratio(aspect_ratio)?;
+    let size = format_size(compute_size(ratio_w, ratio_h, base, Z_IMAGE_TURBO_LIMITS));
+
     // 调试日志：打印计算出的尺寸
     eprintln!("[DEBUG] generate_image: aspect_ratio={}, resolution={}, calculated size={}", aspect_ratio, resolution, size);
     let api_key = std::env::var("MODELSCOPE_API_KEY")
@@ -102,48 +84,107 @@ pub async fn generate_image(
             None,
         ));
     }
-    let prompt = request.prompt.clone();
-    let negative_prompt = request.negative_prompt.clone();
-    let aspect_ratio = request.aspect_ratio.clone();
-    let resolution = request.resolution.clone();
-    let steps = request.steps;
-    let result = modelscope::generate_image_with_zturbo(
-        GenerateImageOptions {
-            prompt: prompt.clone(),
-            negative_prompt: negative_prompt.clone(),
-            size: Some(size),
-            steps,
-        },
-        &api_key,
-    )
-    .await
-    .map_err(|err| {
-        McpError::internal_error(
-            "generate image failed",
-            Some(serde_json::Value::String(err.to_string())),
-        )
-    })?;
+    if request.seed.is_some_and(|seed| seed > MAX_SEED) {
+        return Err(McpError::invalid_params(
+            "seed 取值范围为 0-2147483647",
+            None,
+        ));
+    }
+    if request
+        .guidance
+        .is_some_and(|guidance| !(MIN_GUIDANCE..=MAX_GUIDANCE).contains(&guidance))
+    {
+        return Err(McpError::invalid_params(
+            "guidance 取值范围为 1.5-20",
+            None,
+        ));
+    }
+    if let Some(ref loras) = request.loras {
+        loras
+            .validate(&lora_allowlist())
+            .map_err(|err| McpError::invalid_params(err.to_string(), None))?;
+    }
+    let count = request.n.unwrap_or(1);
+    if count == 0 || count > MAX_IMAGE_COUNT {
+        return Err(McpError::invalid_params(
+            format!("n 取值范围为 1-{MAX_IMAGE_COUNT}"),
+            None,
+        ));
+    }
 
-    let record = AiImageRecord {
-        image_url: result.image_url.clone(),
-        image_type: "generated".to_string(),
-        prompt,
-        negative_prompt,
-        aspect_ratio,
-        resolution,
-        steps,
-        source_image_url: None,
-        created_at: Utc::now().to_rfc3339(),
-    };
-    let _ = save_ai_image_record(_storage, &record).await;
-
-    let response = ToolResponse {
-        url: result.image_url,
-        name: "generated-image".to_string(),
-        mime_type: "image/png".to_string(),
-        text: "图像已生成。".to_string(),
-    };
-    let json = serde_json::to_string(&response).map_err(|err| {
+    let use_cache = request.use_cache.unwrap_or(false);
+    let mut results: Vec<Option<Vec<ToolResponse>>> = std::iter::repeat_with(|| None)
+        .take(count as usize)
+        .collect();
+    let mut jobs = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        // 多张图片时种子依次递增，保证结果互不相同且可复现
+        let seed = request
+            .seed
+            .map(|seed| ((seed as u64 + index as u64) % (MAX_SEED as u64 + 1)) as u32);
+        let job = GenerationJob {
+            index,
+            options: GenerateImageOptions {
+                prompt: request.prompt.clone(),
+                negative_prompt: request.negative_prompt.clone(),
+                size: Some(size.clone()),
+                steps: request.steps,
+                seed,
+                guidance: request.guidance,
+                loras: request.loras.clone(),
+            },
+            aspect_ratio: request.aspect_ratio.clone(),
+            resolution: request.resolution.clone(),
+            use_cache,
+        };
+        // 逐张查找缓存，只为未命中的图片提交生成任务
+        if use_cache {
+            let cache_key_input = generation_cache_key(&job.options, index);
+            if let Some(cached) = read_cached_generation(storage, &cache_key_input).await {
+                results[index as usize] = Some(cached);
+                continue;
+            }
+        }
+        jobs.push(job);
+    }
+
+    let observer = task_observer(&context, jobs.len());
+    let mut join_set = JoinSet::new();
+    for job in jobs {
+        let index = job.index;
+        let storage = storage.clone();
+        let api_key = api_key.clone();
+        let observer = observer.clone();
+        join_set.spawn(async move {
+            let result = generate_single_image(&storage, &api_key, job, &observer).await;
+            (index, result)
+        });
+    }
+
+    while let Some(task_result) = join_set.join_next().await {
+        let (index, result) = task_result.map_err(|err| {
+            McpError::internal_error(
+                "generate image task failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        results[index as usize] = Some(result?);
+    }
+
+    let mut responses = Vec::new();
+    for item in results {
+        match item {
+            Some(items) => responses.extend(items),
+            None => {
+                return Err(McpError::internal_error(
+                    "generate image task missing result",
+                    None,
+                ))
+            }
+        }
+    }
+
+    let json = serde_json::to_string(&responses).map_err(|err| {
         McpError::internal_error(
             "serialize tool response failed",
             Some(serde_json::Value::String(err.to_string())),
@@ -151,3 +192,186 @@ pub async fn generate_image(
     })?;
     Ok(CallToolResult::success(vec![Content::text(json)]))
 }
+
+/// 读取 `MODELSCOPE_LORA_ALLOWLIST`（逗号分隔的仓库 ID），未配置时不限制
+fn lora_allowlist() -> Vec<String> {
+    std::env::var("MODELSCOPE_LORA_ALLOWLIST")
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn generation_cache_key(options: &GenerateImageOptions, index: u32) -> String {
+    format!(
+        "generate:{}",
+        serde_json::json!({
+            "model": modelscope::Z_TURBO_MODEL,
+            "prompt": options.prompt,
+            "negative_prompt": options.negative_prompt,
+            "size": options.size,
+            "steps": options.steps,
+            "seed": options.seed,
+            "guidance": options.guidance,
+            "loras": options.loras,
+            "index": index,
+        })
+    )
+}
+
+/// 读取一次生成任务缓存的全部输出（首张使用缓存键本身，其余为 `#序号`），首张不存在时返回 None
+async fn read_cached_generation(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+) -> Option<Vec<ToolResponse>> {
+    let mut responses = Vec::new();
+    for output_index in 0.. {
+        let output_key_input = if output_index == 0 {
+            cache_key_input.to_string()
+        } else {
+            format!("{cache_key_input}#{output_index}")
+        };
+        let prefix = format!("generated/{}", compute_hash(&output_key_input));
+        let meta_key = LocalFileStorage::get_meta_key(&prefix);
+        let Ok(Some(meta_bytes)) = storage.get(&meta_key).await else {
+            break;
+        };
+        let Ok(metadata) = serde_json::from_slice::<GeneratedImageCacheMetadata>(&meta_bytes)
+        else {
+            break;
+        };
+        if !storage.exists(&metadata.cached_image_key).await.unwrap_or(false) {
+            break;
+        }
+        responses.push(ToolResponse {
+            url: metadata.cached_image_url,
+            name: "generated-image".to_string(),
+            mime_type: metadata.mime_type,
+            text: "图像已生成（命中缓存，未消耗额度）。".to_string(),
+        });
+    }
+    (!responses.is_empty()).then_some(responses)
+}
+
+struct GenerationJob {
+    index: u32,
+    options: GenerateImageOptions,
+    aspect_ratio: Option<String>,
+    resolution: Option<String>,
+    use_cache: bool,
+}
+
+async fn generate_single_image(
+    storage: &LocalFileStorage,
+    api_key: &str,
+    job: GenerationJob,
+    observer: &TaskObserver,
+) -> Result<Vec<ToolResponse>, McpError> {
+    let cache_key_input = generation_cache_key(&job.options, job.index);
+
+    let prompt = job.options.prompt.clone();
+    let negative_prompt = job.options.negative_prompt.clone();
+    let steps = job.options.steps;
+    let seed = job.options.seed;
+    let loras = job.options.loras.clone();
+    let result = modelscope::generate_image_with_zturbo(job.options, api_key, observer)
+        .await
+        .map_err(|err| {
+            McpError::internal_error(
+                "generate image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+
+    let mut responses = Vec::with_capacity(result.image_urls.len());
+    for (output_index, upstream_image_url) in result.image_urls.iter().enumerate() {
+        // 未开启缓存时按 task_id 落盘，避免不同请求互相覆盖
+        let output_key_input = if !job.use_cache {
+            format!("generated:{}:{}", result.task_id, output_index)
+        } else if output_index == 0 {
+            cache_key_input.clone()
+        } else {
+            format!("{cache_key_input}#{output_index}")
+        };
+        let (image_url, mime_type, text) =
+            match persist_generated_image(storage, &output_key_input, upstream_image_url).await {
+                Ok(metadata) => (
+                    metadata.cached_image_url,
+                    metadata.mime_type,
+                    "图像已生成。".to_string(),
+                ),
+                Err(err) => {
+                    eprintln!(
+                        "[WARN] generate_image: cache output failed, falling back to upstream url: {:?}",
+                        err
+                    );
+                    (
+                        upstream_image_url.clone(),
+                        "image/png".to_string(),
+                        "图像已生成（缓存失败，返回的是魔搭临时地址，可能会过期）。".to_string(),
+                    )
+                }
+            };
+
+        let record = AiImageRecord {
+            image_url: image_url.clone(),
+            image_type: "generated".to_string(),
+            prompt: prompt.clone(),
+            negative_prompt: negative_prompt.clone(),
+            aspect_ratio: job.aspect_ratio.clone(),
+            resolution: job.resolution.clone(),
+            steps,
+            seed,
+            loras: loras.clone(),
+            source_image_url: None,
+            source_image_urls: Vec::new(),
+            created_at: Utc::now().to_rfc3339(),
+        };
+        let _ = save_ai_image_record(storage, &record).await;
+
+        responses.push(ToolResponse {
+            url: image_url,
+            name: "generated-image".to_string(),
+            mime_type,
+            text,
+        });
+    }
+    Ok(responses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> GenerateImageOptions {
+        GenerateImageOptions {
+            prompt: "a cat".to_string(),
+            negative_prompt: None,
+            size: Some("1024x1024".to_string()),
+            steps: Some(8),
+            seed: Some(42),
+            guidance: None,
+            loras: None,
+        }
+    }
+
+    #[test]
+    fn generation_cache_key_distinguishes_index_and_options() {
+        let base = generation_cache_key(&options(), 0);
+        assert_eq!(base, generation_cache_key(&options(), 0));
+        assert_ne!(generation_cache_key(&options(), 1), base);
+
+        let mut guided = options();
+        guided.guidance = Some(4.0);
+        assert_ne!(generation_cache_key(&guided, 0), base);
+
+        let mut with_lora = options();
+        with_lora.loras = Some(Loras::Single("owner/name".to_string()));
+        assert_ne!(generation_cache_key(&with_lora, 0), base);
+    }
+}
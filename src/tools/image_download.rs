@@ -0,0 +1,44 @@
+use rmcp::ErrorData as McpError;
+
+use crate::image_processing;
+
+pub struct DownloadedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// 下载图片并识别 MIME 类型（优先按文件头识别，其次使用 Content-Type）
+pub async fn download_image(url: &str) -> Result<DownloadedImage, McpError> {
+    let response = reqwest::get(url).await.map_err(|err| {
+        McpError::internal_error(
+            "fetch image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(McpError::internal_error(
+            "fetch image failed",
+            Some(serde_json::Value::String(format!("HTTP {status}"))),
+        ));
+    }
+    let headers = response.headers().clone();
+    let bytes = response.bytes().await.map_err(|err| {
+        McpError::internal_error(
+            "read image bytes failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let mime_from_header = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
+    let detected = image_processing::detect_mime_type(bytes.as_ref()).map(str::to_string);
+    let mime_type = detected
+        .or(mime_from_header)
+        .ok_or_else(|| McpError::internal_error("unsupported image type", None))?;
+    Ok(DownloadedImage {
+        bytes: bytes.to_vec(),
+        mime_type,
+    })
+}
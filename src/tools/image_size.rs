@@ -0,0 +1,130 @@
+use rmcp::ErrorData as McpError;
+
+/// 模型支持的输出边长范围（像素）
+#[derive(Debug, Clone, Copy)]
+pub struct SizeLimits {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Z-Image-Turbo 的分辨率范围是 [512x512, 2048x2048]
+pub const Z_IMAGE_TURBO_LIMITS: SizeLimits = SizeLimits { min: 512, max: 2048 };
+/// Qwen-Image 系列（含 Qwen-Image-Edit）的分辨率范围是 [64x64, 1664x1664]
+pub const QWEN_IMAGE_EDIT_LIMITS: SizeLimits = SizeLimits { min: 64, max: 1664 };
+
+pub const ASPECT_RATIO_HINT: &str = "aspect_ratio 仅支持 1:1、16:9、9:16、4:3、3:4、3:2、2:3";
+pub const RESOLUTION_HINT: &str = "resolution 仅支持 1k、2k、4k";
+
+/// 解析分辨率档位为长边基准像素，超出模型上限时降级
+pub fn parse_resolution(resolution: &str, limits: SizeLimits) -> Result<f64, McpError> {
+    let base: f64 = match resolution.trim().to_ascii_lowercase().as_str() {
+        "1k" => 1024.0,
+        "2k" => 2048.0,
+        "4k" => 4096.0,
+        _ => return Err(McpError::invalid_params(RESOLUTION_HINT, None)),
+    };
+    Ok(base.min(limits.max as f64))
+}
+
+pub fn parse_aspect_ratio(aspect_ratio: &str) -> Result<(f64, f64), McpError> {
+    match aspect_ratio.trim() {
+        "1:1" => Ok((1.0, 1.0)),
+        "16:9" => Ok((16.0, 9.0)),
+        "9:16" => Ok((9.0, 16.0)),
+        "4:3" => Ok((4.0, 3.0)),
+        "3:4" => Ok((3.0, 4.0)),
+        "3:2" => Ok((3.0, 2.0)),
+        "2:3" => Ok((2.0, 3.0)),
+        _ => Err(McpError::invalid_params(ASPECT_RATIO_HINT, None)),
+    }
+}
+
+/// 按比例与长边基准计算尺寸，并保证两边都落在模型范围内
+pub fn compute_size(ratio_w: f64, ratio_h: f64, base: f64, limits: SizeLimits) -> (u32, u32) {
+    let max_dimension = limits.max as f64;
+    let min_dimension = limits.min as f64;
+    let scale = base / ratio_w.max(ratio_h);
+    let mut w = (ratio_w * scale).round();
+    let mut h = (ratio_h * scale).round();
+
+    // 如果任一维度超过最大限制，按比例缩小
+    if w > max_dimension || h > max_dimension {
+        let scale_down = max_dimension / w.max(h);
+        w = (w * scale_down).round();
+        h = (h * scale_down).round();
+    }
+    // 短边不足最小限制时按比例放大；极端比例下放大后长边仍以上限为准
+    if w < min_dimension || h < min_dimension {
+        let scale_up = min_dimension / w.min(h).max(1.0);
+        w = (w * scale_up).round().min(max_dimension);
+        h = (h * scale_up).round().min(max_dimension);
+    }
+
+    (w as u32, h as u32)
+}
+
+/// 校验调用方直接给出的 `WxH` 尺寸，并按比例收敛到模型范围内
+pub fn normalize_explicit_size(size: &str, limits: SizeLimits) -> Result<(u32, u32), McpError> {
+    let normalized = size.trim().to_ascii_lowercase().replace(['*', '×'], "x");
+    let parsed = normalized
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+        .filter(|(w, h)| *w > 0 && *h > 0);
+    let Some((w, h)) = parsed else {
+        return Err(McpError::invalid_params(
+            "size 格式应为 宽x高，例如 1024x1024",
+            None,
+        ));
+    };
+    let base = (w.max(h) as f64).min(limits.max as f64);
+    Ok(compute_size(w as f64, h as f64, base, limits))
+}
+
+pub fn format_size((width, height): (u32, u32)) -> String {
+    // 注意：API 文档示例使用 'x' 作为分隔符，如 "1024x1024"
+    format!("{}x{}", width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_size_uses_base_for_the_long_edge() {
+        assert_eq!(compute_size(1.0, 1.0, 1024.0, Z_IMAGE_TURBO_LIMITS), (1024, 1024));
+        assert_eq!(compute_size(16.0, 9.0, 1024.0, Z_IMAGE_TURBO_LIMITS), (1024, 576));
+        assert_eq!(compute_size(3.0, 4.0, 2048.0, Z_IMAGE_TURBO_LIMITS), (1536, 2048));
+    }
+
+    #[test]
+    fn compute_size_clamps_to_model_limits() {
+        // 超出上限时按比例缩小
+        assert_eq!(compute_size(4000.0, 3000.0, 4000.0, QWEN_IMAGE_EDIT_LIMITS), (1664, 1248));
+        // 短边不足下限时按比例放大
+        assert_eq!(compute_size(16.0, 9.0, 512.0, Z_IMAGE_TURBO_LIMITS), (910, 512));
+        // 极端比例下长边仍不超过上限
+        let (w, h) = compute_size(10.0, 1.0, 1024.0, Z_IMAGE_TURBO_LIMITS);
+        assert_eq!((w, h), (2048, 512));
+    }
+
+    #[test]
+    fn normalize_explicit_size_accepts_common_separators() {
+        assert_eq!(
+            normalize_explicit_size("800x600", QWEN_IMAGE_EDIT_LIMITS).unwrap(),
+            (800, 600)
+        );
+        assert_eq!(
+            normalize_explicit_size(" 3200*1800 ", QWEN_IMAGE_EDIT_LIMITS).unwrap(),
+            (1664, 936)
+        );
+        assert!(normalize_explicit_size("1024", QWEN_IMAGE_EDIT_LIMITS).is_err());
+        assert!(normalize_explicit_size("0x100", QWEN_IMAGE_EDIT_LIMITS).is_err());
+    }
+
+    #[test]
+    fn parse_resolution_downgrades_beyond_model_limit() {
+        assert_eq!(parse_resolution("4K", Z_IMAGE_TURBO_LIMITS).unwrap(), 2048.0);
+        assert_eq!(parse_resolution("2k", QWEN_IMAGE_EDIT_LIMITS).unwrap(), 1664.0);
+        assert!(parse_resolution("8k", Z_IMAGE_TURBO_LIMITS).is_err());
+    }
+}
@@ -1,12 +1,20 @@
 use rmcp::{
     ErrorData as McpError,
     handler::server::wrapper::Parameters,
-    model::{CallToolResult, Content},
+    model::CallToolResult,
     schemars::JsonSchema,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{modelscope, tools::validate_http_url};
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    image_processing,
+    modelscope::{self, BoundingBox},
+    tools::{
+        draw_boxes, image_data_url, model_image_max_edge, parse_colors, persist_processed_png,
+        read_or_download_image, validate_http_url,
+    },
+};
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct LocateObjectRequest {
@@ -14,13 +22,114 @@ pub struct LocateObjectRequest {
     pub image_url: String,
     #[schemars(description = "需要定位的物体名称")]
     pub object_name: String,
+    #[schemars(description = "是否在图片上绘制边界框与序号并返回标注图，默认 false")]
+    pub annotate: Option<bool>,
+    #[schemars(description = "标注颜色列表（#RRGGBB 或 red、green、blue 等），按序号循环使用")]
+    pub colors: Option<Vec<String>>,
+    #[schemars(description = "标注线宽（像素），默认按图片尺寸自动选择")]
+    pub line_width: Option<u32>,
+}
+
+/// 像素坐标（原点为图片左上角，x2/y2 为不含端点的右下角）
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct PixelBox {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PixelBox {
+    pub fn from_rect((x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        Self {
+            x1: x,
+            y1: y,
+            x2: x + width,
+            y2: y + height,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LocatedObject {
+    /// 序号（从 1 开始，与标注图中的数字一致）
+    pub index: usize,
+    /// 目标标签，模型未给出时为请求的物体名称
+    pub label: String,
+    /// 模型给出的置信度（0-1），未给出时为 null
+    pub confidence: Option<f32>,
+    /// Qwen3 归一化坐标（0-999），可直接传给 crop_image
+    pub normalized: NormalizedBox,
+    /// 像素坐标，区域为空时为 null
+    pub pixel: Option<PixelBox>,
+}
+
+impl LocatedObject {
+    /// `index` 从 0 开始，`fallback_label` 用于模型未给出标签的情况
+    pub fn new(
+        index: usize,
+        bbox: &BoundingBox,
+        rect: Option<(u32, u32, u32, u32)>,
+        fallback_label: &str,
+    ) -> Self {
+        Self {
+            index: index + 1,
+            label: bbox
+                .label
+                .clone()
+                .unwrap_or_else(|| fallback_label.to_string()),
+            confidence: bbox.confidence,
+            normalized: NormalizedBox::from(bbox),
+            pixel: rect.map(PixelBox::from_rect),
+        }
+    }
+}
+
+/// Qwen3 归一化坐标（0-999，原点为图片左上角）
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct NormalizedBox {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl From<&BoundingBox> for NormalizedBox {
+    fn from(bbox: &BoundingBox) -> Self {
+        Self {
+            x1: bbox.x1,
+            y1: bbox.y1,
+            x2: bbox.x2,
+            y2: bbox.y2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LocateObjectOutput {
+    pub image_url: String,
+    pub object_name: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub count: usize,
+    pub objects: Vec<LocatedObject>,
+    /// annotate 为 true 时返回的标注图地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotated_image_url: Option<String>,
 }
 
 pub async fn locate_object(
+    storage: &LocalFileStorage,
     Parameters(request): Parameters<LocateObjectRequest>,
 ) -> Result<CallToolResult, McpError> {
     let validated_url = validate_http_url(&request.image_url)?;
     let validated_url = validated_url.to_string();
+    let annotate = request.annotate.unwrap_or(false);
+    let colors = parse_colors(request.colors.as_deref())?;
     let api_key = std::env::var("MODELSCOPE_API_KEY")
         .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
     if api_key.trim().is_empty() {
@@ -29,8 +138,11 @@ pub async fn locate_object(
             None,
         ));
     }
+    let image = read_or_download_image(storage, &validated_url).await?;
+    // 坐标为 0-999 归一化值，缩放后发送不影响定位结果
+    let model_image_url = image_data_url(&image.bytes, &image.mime_type, model_image_max_edge())?;
     let boxes = modelscope::locate_object_with_qwen(
-        &validated_url,
+        &model_image_url,
         &request.object_name,
         &api_key,
     )
@@ -42,21 +154,57 @@ pub async fn locate_object(
         )
     })?;
 
-    let mut lines = Vec::new();
-    lines.push(format!("定位目标：{}", request.object_name));
-    lines.push(format!("共找到 {} 个目标。", boxes.len()));
-    for (index, bbox) in boxes.iter().enumerate() {
-        lines.push(format!(
-            "{}. x1={:.2}, y1={:.2}, x2={:.2}, y2={:.2}",
-            index + 1,
-            bbox.x1,
-            bbox.y1,
-            bbox.x2,
-            bbox.y2
-        ));
-    }
+    let (mut pixels, width, height) =
+        image_processing::decode_image(&image.bytes, &image.mime_type).map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let rects: Vec<_> = boxes
+        .iter()
+        .map(|bbox| bbox.to_pixel_rect(width, height))
+        .collect();
+
+    let annotated_image_url = if annotate && !boxes.is_empty() {
+        let cache_key_input = format!(
+            "annotate:{}:{}:{}",
+            validated_url,
+            compute_bytes_hash(&image.bytes),
+            serde_json::json!({
+                "boxes": boxes,
+                "colors": colors,
+                "line_width": request.line_width,
+            })
+        );
+        draw_boxes(&mut pixels, width, height, &rects, &colors, request.line_width);
+        Some(persist_processed_png(storage, &cache_key_input, &pixels, width, height).await?)
+    } else {
+        None
+    };
 
-    Ok(CallToolResult::success(vec![Content::text(
-        lines.join("\n"),
-    )]))
+    let objects = boxes
+        .iter()
+        .zip(&rects)
+        .enumerate()
+        .map(|(index, (bbox, rect))| {
+            LocatedObject::new(index, bbox, *rect, &request.object_name)
+        })
+        .collect();
+    let output = LocateObjectOutput {
+        image_url: validated_url,
+        object_name: request.object_name,
+        image_width: width,
+        image_height: height,
+        count: boxes.len(),
+        objects,
+        annotated_image_url,
+    };
+    let value = serde_json::to_value(&output).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::structured(value))
 }
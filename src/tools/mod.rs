@@ -1,10 +1,27 @@
+pub mod ai_output;
+pub mod annotate;
+pub mod ask_image;
+pub mod box_filter;
+pub mod compare_images;
+pub mod count_objects;
 pub mod crop_image;
 pub mod edit_image;
+pub mod extract_objects;
 pub mod fetch_image;
 pub mod generate_image;
+pub mod image_download;
+pub mod image_size;
 pub mod locate_object;
+pub mod model_input;
 pub mod ocr_extract;
+pub mod point_object;
+pub mod processed_output;
+pub mod progress;
+pub mod redact_image;
+pub mod region;
 pub mod rotate_image;
+pub mod spatial_relations;
+pub mod strip_metadata;
 pub mod url_validation;
 // pub mod list_ai_images;
 
@@ -19,12 +36,34 @@ pub struct ToolResponse {
     pub text: String,
 }
 
+pub use ai_output::{
+    persist_edited_image, persist_edited_image_bytes, persist_generated_image, read_cached_image,
+    read_or_download_image,
+};
+pub use annotate::{draw_boxes, parse_color, parse_colors};
+pub use ask_image::{ask_image, AskImageRequest};
+pub use box_filter::{box_iou, filter_min_size, non_max_suppression};
+pub use compare_images::{compare_images, CompareImagesRequest};
+pub use count_objects::{count_objects, CountObjectsOutput, CountObjectsRequest};
 pub use crop_image::{crop_image, CropImageRequest};
 pub use edit_image::{edit_image, EditImageRequest};
+pub use extract_objects::{extract_objects, pad_rect, ExtractObjectsOutput, ExtractObjectsRequest};
 pub use fetch_image::{fetch_image, FetchImageRequest};
 pub use generate_image::{generate_image, GenerateImageRequest};
-pub use locate_object::{locate_object, LocateObjectRequest};
+pub use image_download::{download_image, DownloadedImage};
+pub use locate_object::{
+    locate_object, LocateObjectOutput, LocateObjectRequest, LocatedObject, NormalizedBox,
+    PixelBox,
+};
+pub use model_input::{image_data_url, model_image_max_edge};
 pub use ocr_extract::{ocr_extract, OcrExtractRequest};
+pub use point_object::{point_object, PointObjectOutput, PointObjectRequest};
+pub use processed_output::{persist_processed_bytes, persist_processed_png};
+pub use progress::task_observer;
+pub use redact_image::{redact_image, RedactImageRequest};
+pub use region::RegionBox;
 pub use rotate_image::{rotate_image, RotateImageRequest, RotateDirection};
+pub use spatial_relations::{spatial_relations, SpatialRelationsOutput, SpatialRelationsRequest};
+pub use strip_metadata::{strip_metadata, StripMetadataRequest};
 pub use url_validation::validate_http_url;
 // pub use list_ai_images::{list_ai_images, ListAiImagesRequest};
@@ -0,0 +1,64 @@
+use base64::Engine;
+use rmcp::ErrorData as McpError;
+
+use crate::image_processing;
+
+/// 读取 `MODEL_IMAGE_MAX_EDGE`：发送给视觉模型前图片长边的上限（像素），未配置时不缩放
+pub fn model_image_max_edge() -> Option<u32> {
+    std::env::var("MODEL_IMAGE_MAX_EDGE")
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .filter(|value| *value > 0)
+}
+
+/// 将已下载的图片编码为 data URL 直接放进对话请求，避免模型服务端再次拉取原始 URL。
+/// 需要缩放、需要按 EXIF 摆正或格式不被模型接受（gif/bmp）时转码为 PNG。
+pub fn image_data_url(
+    bytes: &[u8],
+    mime_type: &str,
+    max_edge: Option<u32>,
+) -> Result<String, McpError> {
+    let (width, height) = image_processing::get_dimensions(bytes, mime_type).map_err(|err| {
+        McpError::internal_error(
+            "decode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let needs_downscale = max_edge.is_some_and(|max_edge| width.max(height) > max_edge);
+    let passthrough = matches!(mime_type, "image/png" | "image/jpeg" | "image/webp")
+        && !image_processing::needs_reorientation(bytes, mime_type);
+    if !needs_downscale && passthrough {
+        return Ok(encode_data_url(bytes, mime_type));
+    }
+
+    let (pixels, width, height) = image_processing::decode_image(bytes, mime_type).map_err(|err| {
+        McpError::internal_error(
+            "decode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let (pixels, width, height) = match max_edge {
+        Some(max_edge) => image_processing::downscale_to_max_edge(&pixels, width, height, max_edge)
+            .map_err(|err| {
+                McpError::internal_error(
+                    "resize image failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?,
+        None => (pixels, width, height),
+    };
+    let png = image_processing::encode_png(&pixels, width, height).map_err(|err| {
+        McpError::internal_error(
+            "encode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(encode_data_url(&png, "image/png"))
+}
+
+fn encode_data_url(bytes: &[u8], mime_type: &str) -> String {
+    format!(
+        "data:{mime_type};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
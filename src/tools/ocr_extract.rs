@@ -16,20 +16,54 @@ use crate::{
         compute_hash,
     },
     image_processing,
-    modelscope,
-    tools::validate_http_url,
+    modelscope::{self, BoundingBox, OcrOutputMode, OcrTable, OcrTextBlock},
+    tools::{
+        RegionBox, image_data_url, model_image_max_edge, read_or_download_image,
+        validate_http_url,
+    },
 };
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct OcrExtractRequest {
     #[schemars(description = "图像URL列表")]
     pub urls: Vec<String>,
+    #[schemars(description = "输出模式：markdown（默认，保留结构）、plain（纯文本）、json_layout（逐行文字及 0-999 归一化边界框与置信度）、tables（表格的二维数组与 CSV）")]
+    pub output: Option<OcrOutputMode>,
+    #[schemars(description = "只识别该区域（Qwen3 坐标，0-999），会先在本地裁剪再识别；json_layout 返回的坐标仍相对整张图片")]
+    pub region: Option<RegionBox>,
+    #[schemars(description = "文字语言提示，例如 中文、English、日本語")]
+    pub language: Option<String>,
+    #[schemars(description = "发送给模型前将图片长边缩小到该像素值以内，默认使用服务端配置（未配置则不缩放）")]
+    pub max_edge: Option<u32>,
+}
+
+/// 单张图片 OCR 共用的识别选项
+#[derive(Debug, Clone)]
+struct OcrOptions {
+    mode: OcrOutputMode,
+    region: Option<RegionBox>,
+    language: Option<String>,
+    max_edge: Option<u32>,
+}
+
+/// 识别结果：文本以及按模式附带的结构化内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OcrOutput {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blocks: Option<Vec<OcrTextBlock>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tables: Option<Vec<OcrTable>>,
 }
 
 #[derive(Debug, Serialize, JsonSchema)]
 struct OcrResult {
     pub image_url: String,
     pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<OcrTextBlock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<Vec<OcrTable>>,
     pub cached_text_url: Option<String>,
 }
 
@@ -41,13 +75,30 @@ pub async fn ocr_extract(
         return Err(McpError::invalid_params("urls不能为空", None));
     }
 
+    if let Some(region) = request.region {
+        region.validate()?;
+    }
+
     let total = request.urls.len();
+    let options = OcrOptions {
+        mode: request.output.unwrap_or_default(),
+        region: request.region,
+        language: request
+            .language
+            .map(|language| language.trim().to_string())
+            .filter(|language| !language.is_empty()),
+        max_edge: request
+            .max_edge
+            .filter(|max_edge| *max_edge > 0)
+            .or_else(model_image_max_edge),
+    };
     let mut join_set = JoinSet::new();
 
     for (index, url) in request.urls.into_iter().enumerate() {
         let storage = storage.clone();
+        let options = options.clone();
         join_set.spawn(async move {
-            let result = ocr_single_image(&storage, &url).await;
+            let result = ocr_single_image(&storage, &url, &options).await;
             (index, result)
         });
     }
@@ -91,30 +142,46 @@ pub async fn ocr_extract(
 async fn ocr_single_image(
     storage: &LocalFileStorage,
     raw_url: &str,
+    options: &OcrOptions,
 ) -> Result<OcrResult, McpError> {
     let validated_url = validate_http_url(raw_url)?;
     let validated_url = validated_url.to_string();
-    let cache_key_input = format!("ocr:{}", validated_url);
+    let mode = options.mode;
+    // 默认选项沿用原有缓存 key，其余组合按全部选项区分，避免不同模式结果互相覆盖
+    let cache_key_input =
+        if mode == OcrOutputMode::Markdown
+            && options.region.is_none()
+            && options.language.is_none()
+            && options.max_edge.is_none()
+        {
+            format!("ocr:{}", validated_url)
+        } else {
+            format!(
+                "ocr:{}",
+                serde_json::json!({
+                    "url": validated_url,
+                    "mode": mode.as_str(),
+                    "region": options.region,
+                    "language": options.language,
+                    "max_edge": options.max_edge,
+                })
+            )
+        };
     let hash = compute_hash(&cache_key_input);
     let prefix = format!("ocr/{hash}");
     let meta_key = LocalFileStorage::get_meta_key(&prefix);
-    if let Ok(Some(meta_bytes)) = storage.get(&meta_key).await {
-        if let Ok(metadata) = serde_json::from_slice::<OcrCacheMetadata>(&meta_bytes) {
-            let text_key = metadata.cached_text_key.clone();
-            let text = storage
-                .get(&text_key)
-                .await
-                .ok()
-                .flatten()
-                .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
-            if let Some(text) = text {
-                return Ok(OcrResult {
-                    image_url: validated_url,
-                    text,
-                    cached_text_url: Some(metadata.cached_text_url),
-                });
-            }
-        }
+    if let Ok(Some(meta_bytes)) = storage.get(&meta_key).await
+        && let Ok(metadata) = serde_json::from_slice::<OcrCacheMetadata>(&meta_bytes)
+        && let Ok(Some(cached_bytes)) = storage.get(&metadata.cached_text_key).await
+        && let Some(output) = read_cached_output(mode, &cached_bytes)
+    {
+        return Ok(OcrResult {
+            image_url: validated_url,
+            text: output.text,
+            blocks: output.blocks,
+            tables: output.tables,
+            cached_text_url: Some(metadata.cached_text_url),
+        });
     }
     let api_key = std::env::var("MODELSCOPE_API_KEY")
         .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
@@ -124,46 +191,78 @@ async fn ocr_single_image(
             None,
         ));
     }
-    let response = reqwest::get(&validated_url).await.map_err(|err| {
+    let image = read_or_download_image(storage, &validated_url).await?;
+
+    // 指定区域时先在本地裁剪；图片以 data URL 直接发送给模型，避免模型服务端再次拉取原始 URL
+    let (model_image_url, crop) = match options.region {
+        Some(region) => {
+            let (pixels, width, height) =
+                image_processing::decode_image(&image.bytes, &image.mime_type).map_err(|err| {
+                    McpError::internal_error(
+                        "decode image failed",
+                        Some(serde_json::Value::String(err.to_string())),
+                    )
+                })?;
+            let rect = region
+                .to_pixel_rect(width, height)
+                .ok_or_else(|| McpError::invalid_params("region 区域为空", None))?;
+            let cropped = image_processing::crop_rect(&pixels, width, height, rect).map_err(|err| {
+                McpError::internal_error(
+                    "crop image failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            let png = image_processing::encode_png(&cropped, rect.2, rect.3).map_err(|err| {
+                McpError::internal_error(
+                    "encode image failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            (
+                image_data_url(&png, "image/png", options.max_edge)?,
+                Some((rect, width, height)),
+            )
+        }
+        None => (
+            image_data_url(&image.bytes, &image.mime_type, options.max_edge)?,
+            None,
+        ),
+    };
+
+    let raw = modelscope::extract_image_text_with_qwen(
+        &model_image_url,
+        &api_key,
+        mode,
+        options.language.as_deref(),
+    )
+    .await
+    .map_err(|err| {
         McpError::internal_error(
-            "fetch image failed",
+            "ocr extract failed",
             Some(serde_json::Value::String(err.to_string())),
         )
     })?;
-    let status = response.status();
-    if !status.is_success() {
-        return Err(McpError::internal_error(
-            "fetch image failed",
-            Some(serde_json::Value::String(format!("HTTP {status}"))),
-        ));
+    let mut output = build_output(mode, &raw)?;
+    if let (Some(blocks), Some((rect, width, height))) = (output.blocks.as_mut(), crop) {
+        for block in blocks.iter_mut() {
+            block.bbox = map_crop_bbox(&block.bbox, rect, width, height);
+        }
     }
-    let headers = response.headers().clone();
-    let bytes = response.bytes().await.map_err(|err| {
-        McpError::internal_error(
-            "read image bytes failed",
-            Some(serde_json::Value::String(err.to_string())),
-        )
-    })?;
-    let mime_from_header = headers
-        .get(reqwest::header::CONTENT_TYPE)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
-    let detected = image_processing::detect_mime_type(bytes.as_ref()).map(str::to_string);
-    let _mime_type = detected
-        .or(mime_from_header)
-        .ok_or_else(|| McpError::internal_error("unsupported image type", None))?;
-
-    let text = modelscope::extract_image_text_with_qwen(&validated_url, &api_key)
-        .await
-        .map_err(|err| {
-            McpError::internal_error(
-                "ocr extract failed",
-                Some(serde_json::Value::String(err.to_string())),
-            )
-        })?;
 
-    let text_key = format!("{prefix}/ocr.txt");
-    storage.put(&text_key, text.as_bytes()).await.map_err(|err| {
+    // 结构化模式缓存 JSON，其余模式缓存文本
+    let (text_key, cached_content) = match mode {
+        OcrOutputMode::JsonLayout | OcrOutputMode::Tables => {
+            let json = serde_json::to_string(&output).map_err(|err| {
+                McpError::internal_error(
+                    "serialize ocr output failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            (format!("{prefix}/{}.json", mode.as_str()), json)
+        }
+        _ => (format!("{prefix}/ocr.txt"), output.text.clone()),
+    };
+    storage.put(&text_key, cached_content.as_bytes()).await.map_err(|err| {
         McpError::internal_error(
             "cache text failed",
             Some(serde_json::Value::String(err.to_string())),
@@ -191,7 +290,123 @@ async fn ocr_single_image(
 
     Ok(OcrResult {
         image_url: validated_url,
-        text,
+        text: output.text,
+        blocks: output.blocks,
+        tables: output.tables,
         cached_text_url: Some(cached_text_url),
     })
 }
+
+fn read_cached_output(mode: OcrOutputMode, cached_bytes: &[u8]) -> Option<OcrOutput> {
+    match mode {
+        OcrOutputMode::JsonLayout | OcrOutputMode::Tables => {
+            serde_json::from_slice::<OcrOutput>(cached_bytes).ok()
+        }
+        _ => Some(OcrOutput {
+            text: String::from_utf8_lossy(cached_bytes).to_string(),
+            ..Default::default()
+        }),
+    }
+}
+
+/// 将模型输出整理为返回的文本与结构化内容
+fn build_output(mode: OcrOutputMode, raw: &str) -> Result<OcrOutput, McpError> {
+    match mode {
+        OcrOutputMode::JsonLayout => {
+            let blocks = modelscope::parse_ocr_layout(raw).map_err(|err| {
+                McpError::internal_error(
+                    "parse ocr layout failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            Ok(OcrOutput {
+                text: join_block_text(&blocks),
+                blocks: Some(blocks),
+                tables: None,
+            })
+        }
+        OcrOutputMode::Tables => {
+            let tables = modelscope::parse_ocr_tables(raw).map_err(|err| {
+                McpError::internal_error(
+                    "parse ocr tables failed",
+                    Some(serde_json::Value::String(err.to_string())),
+                )
+            })?;
+            let text = tables
+                .iter()
+                .map(|table| table.csv.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            Ok(OcrOutput {
+                text,
+                blocks: None,
+                tables: Some(tables),
+            })
+        }
+        _ => Ok(OcrOutput {
+            text: raw.to_string(),
+            ..Default::default()
+        }),
+    }
+}
+
+fn join_block_text(blocks: &[OcrTextBlock]) -> String {
+    blocks
+        .iter()
+        .map(|block| block.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 将裁剪区域内的 0-999 坐标换算回整张图片的 0-999 坐标
+fn map_crop_bbox(
+    bbox: &BoundingBox,
+    rect: (u32, u32, u32, u32),
+    width: u32,
+    height: u32,
+) -> BoundingBox {
+    let (rect_x, rect_y, rect_width, rect_height) = rect;
+    let map_x = |value: f32| {
+        (rect_x as f32 + value / 1000.0 * rect_width as f32) / width as f32 * 1000.0
+    };
+    let map_y = |value: f32| {
+        (rect_y as f32 + value / 1000.0 * rect_height as f32) / height as f32 * 1000.0
+    };
+    BoundingBox {
+        x1: map_x(bbox.x1),
+        y1: map_y(bbox.y1),
+        x2: map_x(bbox.x2),
+        y2: map_y(bbox.y2),
+        ..bbox.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_crop_bbox_converts_to_full_image_coordinates() {
+        let bbox = BoundingBox {
+            x1: 0.0,
+            y1: 500.0,
+            x2: 1000.0,
+            y2: 1000.0,
+            label: Some("text".to_string()),
+            confidence: None,
+        };
+        // 在 1000x500 的图片中裁剪右下角 500x250 的区域
+        let mapped = map_crop_bbox(&bbox, (500, 250, 500, 250), 1000, 500);
+        assert_eq!((mapped.x1, mapped.y1, mapped.x2, mapped.y2), (500.0, 750.0, 1000.0, 1000.0));
+        assert_eq!(mapped.label.as_deref(), Some("text"));
+    }
+
+    #[test]
+    fn build_output_joins_table_csv() {
+        let raw = r#"[{"rows": [["a"]]}, {"rows": [["b"]]}]"#;
+        let output = build_output(OcrOutputMode::Tables, raw).unwrap();
+        assert_eq!(output.text, "a\n\nb");
+        assert_eq!(output.tables.map(|tables| tables.len()), Some(2));
+        assert!(output.blocks.is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use rmcp::{
+    ErrorData as McpError, handler::server::wrapper::Parameters, model::CallToolResult,
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::LocalFileStorage,
+    image_processing, modelscope,
+    tools::{image_data_url, model_image_max_edge, read_or_download_image, validate_http_url},
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PointObjectRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+    #[schemars(description = "需要定位的物体或界面元素名称，如 “登录按钮”")]
+    pub object_name: String,
+}
+
+/// Qwen3 归一化点坐标（0-999，原点为图片左上角）
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct NormalizedPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// 像素点坐标（原点为图片左上角）
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct PixelPoint {
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LocatedPoint {
+    /// 序号（从 1 开始）
+    pub index: usize,
+    /// 目标标签，模型未给出时为请求的物体名称
+    pub label: String,
+    /// 模型给出的置信度（0-1），未给出时为 null
+    pub confidence: Option<f32>,
+    pub normalized: NormalizedPoint,
+    pub pixel: PixelPoint,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PointObjectOutput {
+    pub image_url: String,
+    pub object_name: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub count: usize,
+    pub points: Vec<LocatedPoint>,
+}
+
+pub async fn point_object(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<PointObjectRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    let api_key = std::env::var("MODELSCOPE_API_KEY")
+        .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+    if api_key.trim().is_empty() {
+        return Err(McpError::internal_error(
+            "missing MODELSCOPE_API_KEY",
+            None,
+        ));
+    }
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let (width, height) = image_processing::get_dimensions(&image.bytes, &image.mime_type)
+        .map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let model_image_url = image_data_url(&image.bytes, &image.mime_type, model_image_max_edge())?;
+    let points = modelscope::point_object_with_qwen(
+        &model_image_url,
+        &request.object_name,
+        &api_key,
+    )
+    .await
+    .map_err(|err| {
+        McpError::internal_error(
+            "point object failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+
+    let points: Vec<_> = points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let (x, y) = point.to_pixel(width, height);
+            LocatedPoint {
+                index: index + 1,
+                label: point
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| request.object_name.clone()),
+                confidence: point.confidence,
+                normalized: NormalizedPoint {
+                    x: point.x,
+                    y: point.y,
+                },
+                pixel: PixelPoint { x, y },
+            }
+        })
+        .collect();
+    let output = PointObjectOutput {
+        image_url: validated_url,
+        object_name: request.object_name,
+        image_width: width,
+        image_height: height,
+        count: points.len(),
+        points,
+    };
+    let value = serde_json::to_value(&output).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::structured(value))
+}
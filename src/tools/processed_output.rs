@@ -0,0 +1,92 @@
+use chrono::Utc;
+use rmcp::ErrorData as McpError;
+
+use crate::{
+    cache::{
+        LocalFileStorage, ProcessedImageCacheMetadata, compute_hash, get_extension_from_mime_type,
+    },
+    image_processing,
+};
+
+/// 将本地处理结果编码为 PNG 存入 `processed/` 缓存，相同输入直接复用已有结果
+pub async fn persist_processed_png(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<String, McpError> {
+    let prefix = format!("processed/{}", compute_hash(cache_key_input));
+    if let Some(cached_image_url) = cached_processed_url(storage, &prefix).await {
+        return Ok(cached_image_url);
+    }
+
+    let png = image_processing::encode_png(pixels, width, height).map_err(|err| {
+        McpError::internal_error(
+            "encode image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    store_processed(storage, &prefix, cache_key_input, &png, "image/png").await
+}
+
+/// 将已编码的处理结果按原格式存入 `processed/` 缓存，相同输入直接复用已有结果
+pub async fn persist_processed_bytes(
+    storage: &LocalFileStorage,
+    cache_key_input: &str,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<String, McpError> {
+    let prefix = format!("processed/{}", compute_hash(cache_key_input));
+    if let Some(cached_image_url) = cached_processed_url(storage, &prefix).await {
+        return Ok(cached_image_url);
+    }
+    store_processed(storage, &prefix, cache_key_input, bytes, mime_type).await
+}
+
+async fn cached_processed_url(storage: &LocalFileStorage, prefix: &str) -> Option<String> {
+    let meta_key = LocalFileStorage::get_meta_key(prefix);
+    let meta_bytes = storage.get(&meta_key).await.ok().flatten()?;
+    serde_json::from_slice::<ProcessedImageCacheMetadata>(&meta_bytes)
+        .ok()
+        .map(|metadata| metadata.cached_image_url)
+}
+
+async fn store_processed(
+    storage: &LocalFileStorage,
+    prefix: &str,
+    cache_key_input: &str,
+    bytes: &[u8],
+    mime_type: &str,
+) -> Result<String, McpError> {
+    let cached_image_key =
+        LocalFileStorage::get_result_key(prefix, get_extension_from_mime_type(mime_type));
+    storage.put(&cached_image_key, bytes).await.map_err(|err| {
+        McpError::internal_error(
+            "cache processed image failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    let cached_image_url = storage.get_public_url(&cached_image_key);
+    let metadata = ProcessedImageCacheMetadata {
+        cache_key_input: cache_key_input.to_string(),
+        cached_image_key,
+        cached_image_url: cached_image_url.clone(),
+        mime_type: mime_type.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let meta_key = LocalFileStorage::get_meta_key(prefix);
+    let meta_json = serde_json::to_vec(&metadata).map_err(|err| {
+        McpError::internal_error(
+            "serialize cache metadata failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    storage.put(&meta_key, &meta_json).await.map_err(|err| {
+        McpError::internal_error(
+            "save cache metadata failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(cached_image_url)
+}
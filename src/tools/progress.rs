@@ -0,0 +1,140 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rmcp::{
+    RoleServer,
+    model::ProgressNotificationParam,
+    service::RequestContext,
+};
+use tokio::sync::mpsc;
+
+use crate::modelscope::{DEFAULT_TIMEOUT_MS, TaskObserver, TaskProgress};
+
+/// 根据 MCP 请求上下文构建任务观察者：
+/// 请求携带 progressToken 时每次轮询发送 notifications/progress，客户端取消请求时停止轮询。
+/// 同一请求并发多个任务（如生成多张图片）时共用一个 progressToken，进度按所有任务累加，保证单调递增；
+/// `jobs` 为该请求会提交的任务数，total 按任务数 × 单任务超时预先确定，运行中不再变化。
+pub fn task_observer(context: &RequestContext<RoleServer>, jobs: usize) -> TaskObserver {
+    let observer = TaskObserver::new(context.ct.clone());
+    let Some(progress_token) = context.meta.get_progress_token() else {
+        return observer;
+    };
+
+    // 通知经单个转发任务按顺序发送，避免并发发送导致客户端收到的进度乱序
+    let (sender, mut receiver) = mpsc::unbounded_channel::<ProgressNotificationParam>();
+    let peer = context.peer.clone();
+    tokio::spawn(async move {
+        while let Some(param) = receiver.recv().await {
+            if let Err(err) = peer.notify_progress(param).await {
+                eprintln!("[WARN] notify_progress failed: {err}");
+            }
+        }
+    });
+
+    let aggregate = Arc::new(Mutex::new(AggregateProgress::new(
+        jobs,
+        Duration::from_millis(DEFAULT_TIMEOUT_MS),
+    )));
+    observer.with_progress(move |progress| {
+        let Ok(mut aggregate) = aggregate.lock() else {
+            return;
+        };
+        let Some((progress, total)) = aggregate.update(&progress) else {
+            return;
+        };
+        let param = ProgressNotificationParam {
+            progress_token: progress_token.clone(),
+            progress,
+            total: Some(total),
+            message: Some(aggregate.message()),
+        };
+        // 持锁发送，保证进入通道的顺序与进度顺序一致
+        let _ = sender.send(param);
+    })
+}
+
+/// 同一请求下所有任务的进度汇总
+struct AggregateProgress {
+    tasks: BTreeMap<String, TaskProgress>,
+    total: f64,
+    last_progress: f64,
+}
+
+impl AggregateProgress {
+    fn new(jobs: usize, timeout: Duration) -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            total: jobs.max(1) as f64 * timeout.as_secs_f64(),
+            last_progress: 0.0,
+        }
+    }
+
+    /// 记录一次轮询结果，返回累加后的 (progress, total)；进度未增加时返回 None
+    fn update(&mut self, progress: &TaskProgress) -> Option<(f64, f64)> {
+        self.tasks
+            .insert(progress.task_id.clone(), progress.clone());
+        let elapsed: f64 = self
+            .tasks
+            .values()
+            .map(|task| task.elapsed.min(task.timeout).as_secs_f64())
+            .sum();
+        let elapsed = elapsed.min(self.total);
+        if elapsed <= self.last_progress {
+            return None;
+        }
+        self.last_progress = elapsed;
+        Some((elapsed, self.total))
+    }
+
+    fn message(&self) -> String {
+        let statuses = self
+            .tasks
+            .values()
+            .map(|task| {
+                format!(
+                    "{}: {}，已等待 {}s (poll_count={})",
+                    task.task_id,
+                    task.task_status,
+                    task.elapsed.as_secs(),
+                    task.poll_count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("；");
+        format!("ModelScope 任务状态: {statuses}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn progress(task_id: &str, elapsed_secs: u64) -> TaskProgress {
+        TaskProgress {
+            task_id: task_id.to_string(),
+            task_status: "RUNNING".to_string(),
+            poll_count: 1,
+            elapsed: Duration::from_secs(elapsed_secs),
+            timeout: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn aggregate_progress_is_monotonic_across_tasks() {
+        let mut aggregate = AggregateProgress::new(2, Duration::from_secs(300));
+        // total 从第一次上报起就是两个任务的总和
+        assert_eq!(aggregate.update(&progress("a", 10)), Some((10.0, 600.0)));
+        // 第二个任务刚开始，单独看进度比第一个小，但累加后仍递增
+        assert_eq!(aggregate.update(&progress("b", 5)), Some((15.0, 600.0)));
+        assert_eq!(aggregate.update(&progress("a", 20)), Some((25.0, 600.0)));
+        // 同一任务重复上报相同的耗时不再发送
+        assert_eq!(aggregate.update(&progress("b", 5)), None);
+        assert!(aggregate.message().contains("a: RUNNING"));
+        assert!(aggregate.message().contains("b: RUNNING"));
+    }
+}
@@ -0,0 +1,176 @@
+use rmcp::{
+    ErrorData as McpError,
+    handler::server::wrapper::Parameters,
+    model::{CallToolResult, Content},
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    image_processing, modelscope,
+    tools::{
+        RegionBox, ToolResponse, image_data_url, model_image_max_edge, pad_rect, parse_color,
+        persist_processed_png, read_or_download_image, validate_http_url,
+    },
+};
+
+/// 模型给出的边界框常常贴着目标边缘，打码时四边各向外扩展 10%，避免边缘露出
+const DETECTED_BOX_PADDING: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactMethod {
+    /// 高斯模糊
+    #[default]
+    Blur,
+    /// 马赛克
+    Pixelate,
+    /// 纯色填充
+    Fill,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RedactImageRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+    #[schemars(description = "需要打码的区域列表（Qwen3 坐标，0-999）")]
+    pub boxes: Option<Vec<RegionBox>>,
+    #[schemars(description = "需要打码的物体描述（如 人脸、车牌、身份证号码），由视觉模型定位，定位框四边各外扩 10%")]
+    pub object: Option<String>,
+    #[schemars(description = "打码方式：blur（高斯模糊，默认）、pixelate（马赛克）、fill（纯色填充）")]
+    pub method: Option<RedactMethod>,
+    #[schemars(
+        description = "打码强度：blur 为模糊半径、pixelate 为马赛克格子边长（像素），默认按区域大小自动选择"
+    )]
+    pub strength: Option<u32>,
+    #[schemars(description = "fill 的填充颜色（#RRGGBB 或颜色名），默认 black")]
+    pub color: Option<String>,
+}
+
+pub async fn redact_image(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<RedactImageRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    let explicit_boxes = request.boxes.unwrap_or_default();
+    let object = request
+        .object
+        .as_deref()
+        .map(str::trim)
+        .filter(|object| !object.is_empty());
+    if explicit_boxes.is_empty() && object.is_none() {
+        return Err(McpError::invalid_params(
+            "boxes 与 object 至少需要提供一个",
+            None,
+        ));
+    }
+    for region in &explicit_boxes {
+        region.validate()?;
+    }
+    let method = request.method.unwrap_or_default();
+    let fill_color = parse_color(request.color.as_deref().unwrap_or("black"))?;
+    if request.strength == Some(0) {
+        return Err(McpError::invalid_params("strength 必须大于 0", None));
+    }
+
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let (mut pixels, width, height) =
+        image_processing::decode_image(&image.bytes, &image.mime_type).map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+
+    let mut rects: Vec<(u32, u32, u32, u32)> = explicit_boxes
+        .iter()
+        .filter_map(|region| region.to_pixel_rect(width, height))
+        .collect();
+    if let Some(object) = object {
+        let api_key = std::env::var("MODELSCOPE_API_KEY")
+            .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+        if api_key.trim().is_empty() {
+            return Err(McpError::internal_error(
+                "missing MODELSCOPE_API_KEY",
+                None,
+            ));
+        }
+        let model_image_url =
+            image_data_url(&image.bytes, &image.mime_type, model_image_max_edge())?;
+        // 模型没有找到目标时仍对显式给出的 boxes 打码
+        let boxes = modelscope::boxes_or_empty(
+            modelscope::locate_object_with_qwen(&model_image_url, object, &api_key).await,
+        )
+        .map_err(|err| {
+            McpError::internal_error(
+                "locate object failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        rects.extend(
+            boxes
+                .iter()
+                .filter_map(|bbox| bbox.to_pixel_rect(width, height))
+                .map(|rect| pad_rect(rect, DETECTED_BOX_PADDING, width, height)),
+        );
+    }
+    // 未找到任何区域时不输出图片，避免调用方误把原图当作已打码结果分享
+    if rects.is_empty() {
+        return Err(McpError::invalid_params(
+            "未找到需要打码的区域，未生成打码图片",
+            None,
+        ));
+    }
+
+    for rect in &rects {
+        let short_side = rect.2.min(rect.3);
+        match method {
+            RedactMethod::Blur => {
+                let sigma = request.strength.unwrap_or((short_side / 4).max(8));
+                image_processing::blur_rect(&mut pixels, width, height, *rect, sigma as f32)
+                    .map_err(|err| {
+                        McpError::internal_error(
+                            "blur image failed",
+                            Some(serde_json::Value::String(err.to_string())),
+                        )
+                    })?;
+            }
+            RedactMethod::Pixelate => {
+                let block = request.strength.unwrap_or((short_side / 8).max(8));
+                image_processing::pixelate_rect(&mut pixels, width, height, *rect, block);
+            }
+            RedactMethod::Fill => {
+                image_processing::fill_rect(&mut pixels, width, height, *rect, fill_color);
+            }
+        }
+    }
+
+    // 只缓存打码后的结果，原图与中间像素不落盘
+    let cache_key_input = format!(
+        "redact:{}:{}:{}",
+        validated_url,
+        compute_bytes_hash(&image.bytes),
+        serde_json::json!({
+            "rects": rects,
+            "method": method,
+            "strength": request.strength,
+            "color": fill_color,
+        })
+    );
+    let url = persist_processed_png(storage, &cache_key_input, &pixels, width, height).await?;
+    let response = ToolResponse {
+        url,
+        name: "redacted-image".to_string(),
+        mime_type: "image/png".to_string(),
+        text: format!("已对 {} 个区域完成打码。", rects.len()),
+    };
+    let json = serde_json::to_string(&response).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::success(vec![Content::text(json)]))
+}
@@ -0,0 +1,89 @@
+use rmcp::{ErrorData as McpError, schemars::JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::modelscope::BoundingBox;
+
+/// Qwen3 归一化坐标（0-999）表示的矩形区域，与 crop_image 的坐标一致
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct RegionBox {
+    #[schemars(description = "左上角 x 坐标（Qwen3 坐标，0-999）")]
+    pub x1: u32,
+    #[schemars(description = "左上角 y 坐标（Qwen3 坐标，0-999）")]
+    pub y1: u32,
+    #[schemars(description = "右下角 x 坐标（Qwen3 坐标，0-999）")]
+    pub x2: u32,
+    #[schemars(description = "右下角 y 坐标（Qwen3 坐标，0-999）")]
+    pub y2: u32,
+}
+
+impl RegionBox {
+    pub const MAX_COORD: u32 = 999;
+
+    pub fn validate(&self) -> Result<(), McpError> {
+        let max_coord = Self::MAX_COORD;
+        if self.x1 > max_coord || self.y1 > max_coord || self.x2 > max_coord || self.y2 > max_coord
+        {
+            return Err(McpError::invalid_params(
+                "coordinates must be within [0, 999]",
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// 换算为像素坐标 (x, y, width, height)，区域为空时返回 None
+    pub fn to_pixel_rect(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        BoundingBox::from(*self).to_pixel_rect(width, height)
+    }
+}
+
+impl From<RegionBox> for BoundingBox {
+    fn from(region: RegionBox) -> Self {
+        BoundingBox {
+            x1: region.x1 as f32,
+            y1: region.y1 as f32,
+            x2: region.x2 as f32,
+            y2: region.y2 as f32,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x1: u32, y1: u32, x2: u32, y2: u32) -> RegionBox {
+        RegionBox { x1, y1, x2, y2 }
+    }
+
+    #[test]
+    fn to_pixel_rect_scales_normalized_coordinates() {
+        assert_eq!(
+            region(100, 200, 500, 600).to_pixel_rect(1000, 500),
+            Some((100, 100, 400, 200))
+        );
+        assert_eq!(region(0, 0, 999, 999).to_pixel_rect(10, 10), Some((0, 0, 9, 9)));
+    }
+
+    #[test]
+    fn to_pixel_rect_swaps_reversed_corners() {
+        assert_eq!(
+            region(500, 600, 100, 200).to_pixel_rect(1000, 500),
+            Some((100, 100, 400, 200))
+        );
+    }
+
+    #[test]
+    fn to_pixel_rect_rejects_empty_regions() {
+        assert_eq!(region(100, 100, 100, 500).to_pixel_rect(1000, 1000), None);
+        // 区域小于一个像素
+        assert_eq!(region(10, 10, 15, 15).to_pixel_rect(50, 50), None);
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_coordinates() {
+        assert!(region(0, 0, 999, 999).validate().is_ok());
+        assert!(region(0, 0, 1000, 999).validate().is_err());
+    }
+}
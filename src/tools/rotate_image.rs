@@ -49,24 +49,24 @@ pub async fn rotate_image(
     let hash = compute_hash(&cache_key_input);
     let prefix = format!("processed/{hash}");
     let meta_key = LocalFileStorage::get_meta_key(&prefix);
-    if let Ok(Some(meta_bytes)) = storage.get(&meta_key).await {
-        if let Ok(metadata) = serde_json::from_slice::<ProcessedImageCacheMetadata>(&meta_bytes) {
-            let response = ToolResponse {
-                url: metadata.cached_image_url,
-                name: "rotated-image".to_string(),
-                mime_type: metadata.mime_type,
-                text: "图像已旋转。".to_string(),
-            };
-            let json = serde_json::to_string(&response).map_err(|err| {
-                McpError::internal_error(
-                    "serialize tool response failed",
-                    Some(serde_json::Value::String(err.to_string())),
-                )
-            })?;
-            return Ok(CallToolResult::success(vec![
-                Content::text(json),
-            ]));
-        }
+    if let Ok(Some(meta_bytes)) = storage.get(&meta_key).await
+        && let Ok(metadata) = serde_json::from_slice::<ProcessedImageCacheMetadata>(&meta_bytes)
+    {
+        let response = ToolResponse {
+            url: metadata.cached_image_url,
+            name: "rotated-image".to_string(),
+            mime_type: metadata.mime_type,
+            text: "图像已旋转。".to_string(),
+        };
+        let json = serde_json::to_string(&response).map_err(|err| {
+            McpError::internal_error(
+                "serialize tool response failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        return Ok(CallToolResult::success(vec![
+            Content::text(json),
+        ]));
     }
     let response = reqwest::get(&validated_url).await.map_err(|err| {
         McpError::internal_error(
@@ -0,0 +1,333 @@
+use std::sync::Arc;
+
+use rmcp::{
+    ErrorData as McpError, handler::server::wrapper::Parameters, model::CallToolResult,
+    schemars::JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::{
+    cache::LocalFileStorage,
+    image_processing,
+    modelscope::{self, BoundingBox},
+    tools::{
+        NormalizedBox, PixelBox, box_iou, image_data_url, model_image_max_edge,
+        read_or_download_image, validate_http_url,
+    },
+};
+
+const MIN_OBJECTS: usize = 2;
+const MAX_OBJECTS: usize = 6;
+/// 中心点差距小于图片边长的该比例时视为同一水平/垂直位置
+const ALIGN_TOLERANCE: f64 = 0.05;
+/// 交集覆盖较小框面积达到该比例时视为包含
+const CONTAINMENT_THRESHOLD: f64 = 0.9;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SpatialRelationsRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+    #[schemars(description = "需要分析相对位置的物体名称列表（2-6 个），如 [\"猫\", \"沙发\"]")]
+    pub objects: Vec<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SpatialNode {
+    /// 节点 ID，格式为 `物体名称#序号`
+    pub id: String,
+    pub object: String,
+    pub label: String,
+    pub normalized: NormalizedBox,
+    pub pixel: PixelBox,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HorizontalRelation {
+    LeftOf,
+    RightOf,
+    Aligned,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerticalRelation {
+    Above,
+    Below,
+    Aligned,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Containment {
+    /// source 包含 target
+    Contains,
+    /// source 位于 target 内
+    Inside,
+    None,
+}
+
+/// 以 source 为主语描述 source 与 target 的关系
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SpatialEdge {
+    pub source: String,
+    pub target: String,
+    pub horizontal: HorizontalRelation,
+    pub vertical: VerticalRelation,
+    pub overlaps: bool,
+    pub iou: f64,
+    pub containment: Containment,
+    /// 中心点之间的像素距离
+    pub distance: f64,
+    /// 中心点距离除以图片对角线长度（0-1）
+    pub normalized_distance: f64,
+    /// source 面积 / target 面积
+    pub size_ratio: f64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SpatialRelationsOutput {
+    pub image_url: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub nodes: Vec<SpatialNode>,
+    pub edges: Vec<SpatialEdge>,
+    /// 未能定位到的物体
+    pub missing: Vec<String>,
+}
+
+pub async fn spatial_relations(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<SpatialRelationsRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    // 重复的物体名称只定位一次，避免生成重复的节点 ID
+    let mut objects: Vec<String> = Vec::with_capacity(request.objects.len());
+    for object in &request.objects {
+        let object = object.trim();
+        if !object.is_empty() && !objects.iter().any(|existing| existing == object) {
+            objects.push(object.to_string());
+        }
+    }
+    if !(MIN_OBJECTS..=MAX_OBJECTS).contains(&objects.len()) {
+        return Err(McpError::invalid_params(
+            format!("objects 需要 {MIN_OBJECTS}-{MAX_OBJECTS} 个不同的物体名称"),
+            None,
+        ));
+    }
+    let api_key = std::env::var("MODELSCOPE_API_KEY")
+        .map_err(|_| McpError::internal_error("missing MODELSCOPE_API_KEY", None))?;
+    if api_key.trim().is_empty() {
+        return Err(McpError::internal_error(
+            "missing MODELSCOPE_API_KEY",
+            None,
+        ));
+    }
+
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let (width, height) = image_processing::get_dimensions(&image.bytes, &image.mime_type)
+        .map_err(|err| {
+            McpError::internal_error(
+                "decode image failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+    let model_image_url = Arc::new(image_data_url(
+        &image.bytes,
+        &image.mime_type,
+        model_image_max_edge(),
+    )?);
+
+    let mut join_set = JoinSet::new();
+    for (index, object) in objects.iter().enumerate() {
+        let model_image_url = Arc::clone(&model_image_url);
+        let object = object.clone();
+        let api_key = api_key.clone();
+        join_set.spawn(async move {
+            let result =
+                modelscope::locate_object_with_qwen(&model_image_url, &object, &api_key).await;
+            (index, result)
+        });
+    }
+    let mut located = vec![None; objects.len()];
+    while let Some(joined) = join_set.join_next().await {
+        let (index, result) = joined.map_err(|err| {
+            McpError::internal_error(
+                "locate task failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        // 模型没有找到该物体时记为缺失，请求、鉴权等错误直接返回
+        let boxes = modelscope::boxes_or_empty(result).map_err(|err| {
+            McpError::internal_error(
+                "locate object failed",
+                Some(serde_json::Value::String(err.to_string())),
+            )
+        })?;
+        located[index] = Some(boxes);
+    }
+
+    let mut nodes = Vec::new();
+    let mut missing = Vec::new();
+    for (object, boxes) in objects.iter().zip(located) {
+        let mut found = 0;
+        for bbox in boxes.unwrap_or_default() {
+            let Some(rect) = bbox.to_pixel_rect(width, height) else {
+                continue;
+            };
+            found += 1;
+            nodes.push(SpatialNode {
+                id: format!("{object}#{found}"),
+                object: object.clone(),
+                label: bbox.label.clone().unwrap_or_else(|| object.clone()),
+                normalized: NormalizedBox::from(&bbox),
+                pixel: PixelBox::from_rect(rect),
+            });
+        }
+        if found == 0 {
+            missing.push(object.clone());
+        }
+    }
+
+    let mut edges = Vec::new();
+    for (index, source) in nodes.iter().enumerate() {
+        for target in &nodes[index + 1..] {
+            edges.push(relate(source, target, width, height));
+        }
+    }
+
+    let output = SpatialRelationsOutput {
+        image_url: validated_url,
+        image_width: width,
+        image_height: height,
+        nodes,
+        edges,
+        missing,
+    };
+    let value = serde_json::to_value(&output).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::structured(value))
+}
+
+fn relate(source: &SpatialNode, target: &SpatialNode, width: u32, height: u32) -> SpatialEdge {
+    let a = &source.pixel;
+    let b = &target.pixel;
+    let center = |bbox: &PixelBox| {
+        (
+            (bbox.x1 + bbox.x2) as f64 / 2.0,
+            (bbox.y1 + bbox.y2) as f64 / 2.0,
+        )
+    };
+    let ((ax, ay), (bx, by)) = (center(a), center(b));
+
+    let horizontal = if (ax - bx).abs() <= width as f64 * ALIGN_TOLERANCE {
+        HorizontalRelation::Aligned
+    } else if ax < bx {
+        HorizontalRelation::LeftOf
+    } else {
+        HorizontalRelation::RightOf
+    };
+    let vertical = if (ay - by).abs() <= height as f64 * ALIGN_TOLERANCE {
+        VerticalRelation::Aligned
+    } else if ay < by {
+        VerticalRelation::Above
+    } else {
+        VerticalRelation::Below
+    };
+
+    let intersection_width = a.x2.min(b.x2).saturating_sub(a.x1.max(b.x1)) as f64;
+    let intersection_height = a.y2.min(b.y2).saturating_sub(a.y1.max(b.y1)) as f64;
+    let intersection = intersection_width * intersection_height;
+    let area_a = a.width as f64 * a.height as f64;
+    let area_b = b.width as f64 * b.height as f64;
+    let iou = box_iou(&pixel_bbox(a), &pixel_bbox(b)) as f64;
+    let covers = |inner_area: f64, outer_area: f64| {
+        inner_area > 0.0
+            && inner_area <= outer_area
+            && intersection / inner_area >= CONTAINMENT_THRESHOLD
+    };
+    let containment = if covers(area_b, area_a) {
+        Containment::Contains
+    } else if covers(area_a, area_b) {
+        Containment::Inside
+    } else {
+        Containment::None
+    };
+
+    let distance = ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt();
+    let diagonal = ((width as f64).powi(2) + (height as f64).powi(2)).sqrt();
+    SpatialEdge {
+        source: source.id.clone(),
+        target: target.id.clone(),
+        horizontal,
+        vertical,
+        overlaps: intersection > 0.0,
+        iou,
+        containment,
+        distance,
+        normalized_distance: if diagonal > 0.0 { distance / diagonal } else { 0.0 },
+        size_ratio: if area_b > 0.0 { area_a / area_b } else { 0.0 },
+    }
+}
+
+fn pixel_bbox(pixel: &PixelBox) -> BoundingBox {
+    BoundingBox {
+        x1: pixel.x1 as f32,
+        y1: pixel.y1 as f32,
+        x2: pixel.x2 as f32,
+        y2: pixel.y2 as f32,
+        label: None,
+        confidence: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, rect: (u32, u32, u32, u32)) -> SpatialNode {
+        SpatialNode {
+            id: id.to_string(),
+            object: id.to_string(),
+            label: id.to_string(),
+            normalized: NormalizedBox {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 0.0,
+                y2: 0.0,
+            },
+            pixel: PixelBox::from_rect(rect),
+        }
+    }
+
+    #[test]
+    fn relate_reports_direction_and_containment() {
+        let sofa = node("sofa", (100, 300, 600, 300));
+        let cat = node("cat", (150, 310, 100, 100));
+        let edge = relate(&sofa, &cat, 1000, 1000);
+        assert!(matches!(edge.horizontal, HorizontalRelation::RightOf));
+        assert!(matches!(edge.vertical, VerticalRelation::Below));
+        assert!(matches!(edge.containment, Containment::Contains));
+        assert!(edge.overlaps);
+        assert!((edge.iou - 10_000.0 / 180_000.0).abs() < 1e-6);
+        assert!((edge.size_ratio - 18.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn relate_handles_disjoint_aligned_boxes() {
+        let left = node("left", (0, 0, 100, 100));
+        let right = node("right", (500, 10, 100, 100));
+        let edge = relate(&left, &right, 1000, 1000);
+        assert!(matches!(edge.horizontal, HorizontalRelation::LeftOf));
+        assert!(matches!(edge.vertical, VerticalRelation::Aligned));
+        assert!(matches!(edge.containment, Containment::None));
+        assert!(!edge.overlaps);
+        assert_eq!(edge.iou, 0.0);
+    }
+}
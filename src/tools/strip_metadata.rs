@@ -0,0 +1,69 @@
+use rmcp::{
+    ErrorData as McpError,
+    handler::server::wrapper::Parameters,
+    model::{CallToolResult, Content},
+    schemars::JsonSchema,
+};
+use serde::Deserialize;
+
+use crate::{
+    cache::{LocalFileStorage, compute_bytes_hash},
+    metadata_strip,
+    tools::{
+        ToolResponse, persist_processed_bytes, read_or_download_image, validate_http_url,
+    },
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StripMetadataRequest {
+    #[schemars(description = "图像URL")]
+    pub image_url: String,
+}
+
+pub async fn strip_metadata(
+    storage: &LocalFileStorage,
+    Parameters(request): Parameters<StripMetadataRequest>,
+) -> Result<CallToolResult, McpError> {
+    let validated_url = validate_http_url(&request.image_url)?;
+    let validated_url = validated_url.to_string();
+    let image = read_or_download_image(storage, &validated_url).await?;
+    let stripped = metadata_strip::strip_metadata(&image.bytes, &image.mime_type).map_err(|err| {
+        McpError::internal_error(
+            "strip metadata failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+
+    let cache_key_input = format!(
+        "strip:{}:{}",
+        validated_url,
+        compute_bytes_hash(&image.bytes)
+    );
+    let url =
+        persist_processed_bytes(storage, &cache_key_input, &stripped.bytes, &stripped.mime_type)
+            .await?;
+    let mut text = if stripped.removed.is_empty() {
+        "图片中没有需要去除的元数据。".to_string()
+    } else {
+        format!("已去除元数据：{}。", stripped.removed.join("、"))
+    };
+    if stripped.orientation_baked {
+        text.push_str("已按 EXIF 方向摆正像素并重新编码。");
+        if stripped.mime_type != image.mime_type {
+            text.push_str(&format!("格式已由 {} 转为 {}。", image.mime_type, stripped.mime_type));
+        }
+    }
+    let response = ToolResponse {
+        url,
+        name: "stripped-image".to_string(),
+        mime_type: stripped.mime_type,
+        text,
+    };
+    let json = serde_json::to_string(&response).map_err(|err| {
+        McpError::internal_error(
+            "serialize tool response failed",
+            Some(serde_json::Value::String(err.to_string())),
+        )
+    })?;
+    Ok(CallToolResult::success(vec![Content::text(json)]))
+}
@@ -0,0 +1,155 @@
+//! 缓存元数据与工具返回值共用的数据类型，不依赖模型调用与图片处理实现
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// 最多可叠加的 LoRA 数量
+pub const MAX_LORA_COUNT: usize = 6;
+
+/// LoRA 配置：单个 LoRA 传仓库 ID，多个 LoRA 传仓库 ID 到权重的映射（权重之和须为 1.0）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Loras {
+    Single(String),
+    Weighted(BTreeMap<String, f64>),
+}
+
+impl Loras {
+    pub fn repo_ids(&self) -> Vec<&str> {
+        match self {
+            Loras::Single(repo_id) => vec![repo_id.as_str()],
+            Loras::Weighted(weights) => weights.keys().map(String::as_str).collect(),
+        }
+    }
+
+    /// 校验数量、仓库 ID 与权重；`allowlist` 非空时仅允许其中的仓库
+    pub fn validate(&self, allowlist: &[String]) -> Result<()> {
+        let repo_ids = self.repo_ids();
+        if repo_ids.is_empty() {
+            return Err(anyhow!("loras 不能为空"));
+        }
+        if repo_ids.len() > MAX_LORA_COUNT {
+            return Err(anyhow!("loras 最多支持 {MAX_LORA_COUNT} 个"));
+        }
+        for repo_id in &repo_ids {
+            let trimmed = repo_id.trim();
+            if trimmed.is_empty() || trimmed != *repo_id || trimmed.split('/').count() != 2 {
+                return Err(anyhow!("LoRA 仓库 ID 格式无效: {repo_id}，应为 owner/name"));
+            }
+            if !allowlist.is_empty() && !allowlist.iter().any(|allowed| allowed == repo_id) {
+                return Err(anyhow!("LoRA 不在服务端允许列表中: {repo_id}"));
+            }
+        }
+        if let Loras::Weighted(weights) = self {
+            if weights.values().any(|weight| !weight.is_finite() || *weight <= 0.0) {
+                return Err(anyhow!("LoRA 权重必须为正数"));
+            }
+            let total: f64 = weights.values().sum();
+            if (total - 1.0).abs() > 1e-3 {
+                return Err(anyhow!("LoRA 权重之和必须为 1.0，当前为 {total}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 图片描述中可额外请求的字段
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DescriptionField {
+    /// 关键词标签
+    Tags,
+    /// 最主要的物体
+    DominantObjects,
+    /// 是否包含文字
+    TextPresent,
+    /// 是否包含不适宜公开展示的内容
+    Nsfw,
+}
+
+/// 按请求返回的附加描述字段，未请求或模型未给出时为 None
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DescriptionExtras {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dominant_objects: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_present: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nsfw: Option<bool>,
+}
+
+impl DescriptionExtras {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_none()
+            && self.dominant_objects.is_none()
+            && self.text_present.is_none()
+            && self.nsfw.is_none()
+    }
+}
+
+/// 图片的技术信息，只读取文件头与元数据，不保留解码后的像素
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImageProfile {
+    /// 文件格式，如 png、jpeg、gif、webp、bmp
+    pub format: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub total_pixels: u64,
+    pub aspect_ratio: Option<f64>,
+    /// 文件大小（字节）
+    pub size: usize,
+    /// 文件中存储的原始颜色类型，如 Rgb8、Rgba8、L8
+    pub color_type: String,
+    /// 每个通道的位深
+    pub bit_depth: u16,
+    pub channels: u8,
+    pub has_alpha: bool,
+    /// 帧数，静态图为 1
+    pub frame_count: u32,
+    pub animated: bool,
+    pub has_icc_profile: bool,
+    /// 未包含 EXIF 时为 null
+    pub exif: Option<ExifSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExifSummary {
+    /// EXIF 数据块大小（字节）
+    pub size: usize,
+    /// EXIF 方向值（1-8，1 为正常方向）
+    pub orientation: u8,
+    /// 是否已按方向摆正；为 true 时 width/height 为摆正后的尺寸
+    #[serde(default)]
+    pub orientation_applied: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lens_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>,
+    /// 拍摄时间（`YYYY-MM-DDTHH:MM:SS`，无时区）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps: Option<GpsPosition>,
+}
+
+/// GPS 坐标（WGS84 十进制度数，南纬、西经为负）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 海拔（米），低于海平面为负
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub altitude: Option<f64>,
+}
@@ -9,7 +9,11 @@ use axum::{
 use chrono::Utc;
 use serde::Serialize;
 
-use crate::cache::{LocalFileStorage, compute_hash, get_extension_from_mime_type};
+use crate::{
+    cache::{LocalFileStorage, compute_hash, get_extension_from_mime_type},
+    image_processing,
+    metadata_strip::{self, MetadataPolicy},
+};
 
 const SECRET_HTML: &str = include_str!("../templates/secret.html");
 const UPLOAD_HTML: &str = include_str!("../templates/upload.html");
@@ -97,14 +101,10 @@ pub async fn handle_image_upload(
         }
     }
 
-    let content_type = match content_type {
-        Some(value) => Some(value),
-        None => None,
-    };
-    if let Some(content_type) = content_type.as_deref() {
-        if !content_type.starts_with("image/") {
-            return json_error(StatusCode::BAD_REQUEST, "文件类型不支持");
-        }
+    if let Some(content_type) = content_type.as_deref()
+        && !content_type.starts_with("image/")
+    {
+        return json_error(StatusCode::BAD_REQUEST, "文件类型不支持");
     }
 
     let file_name = match file_name {
@@ -118,8 +118,36 @@ pub async fn handle_image_upload(
     if bytes.is_empty() {
         return json_error(StatusCode::BAD_REQUEST, "上传文件为空");
     }
+    // 上传的图片通过 /cache 公开访问，默认去除 GPS 等元数据；无法识别的格式无法保证去除，直接拒绝
+    // 摆正方向后可能转换了格式，此时扩展名以新格式为准
+    let (bytes, converted_mime_type) = match MetadataPolicy::for_uploads() {
+        MetadataPolicy::Keep => (bytes.to_vec(), None),
+        MetadataPolicy::Strip => {
+            let Some(mime_type) = image_processing::detect_mime_type(&bytes) else {
+                return json_error(
+                    StatusCode::BAD_REQUEST,
+                    "无法识别的图片格式，仅支持 JPEG、PNG、WebP、GIF、BMP",
+                );
+            };
+            match metadata_strip::strip_metadata(&bytes, mime_type) {
+                Ok(stripped) => {
+                    let converted = (stripped.mime_type != mime_type).then_some(stripped.mime_type);
+                    (stripped.bytes, converted)
+                }
+                Err(err) => {
+                    return json_error(
+                        StatusCode::BAD_REQUEST,
+                        &format!("去除图片元数据失败: {err}"),
+                    );
+                }
+            }
+        }
+    };
 
-    let ext = resolve_extension(&file_name, content_type.as_deref());
+    let ext = match &converted_mime_type {
+        Some(mime_type) => get_extension_from_mime_type(mime_type).to_string(),
+        None => resolve_extension(&file_name, content_type.as_deref()),
+    };
     let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or_default();
     let cache_key_input = format!("upload:{timestamp}:{file_name}:{}", bytes.len());
     let hash = compute_hash(&cache_key_input);